@@ -1,2 +1,7 @@
+use crate::util::Context;
+
 pub mod y2018;
 pub mod y2019;
+
+// Name of a challenge, and the function solving it
+pub type Challenge = (&'static str, &'static dyn Fn(&mut Context));
@@ -1,20 +1,26 @@
 use std::collections::HashSet;
 
-use crate::util;
+use crate::util::{self, Context};
 
-pub fn run() {
-  let modulations: Vec<i32> = util::read_file_by_lines(2018, 1)
+pub fn run(context: &mut Context) {
+  let modulations: Vec<i32> = util::read_file_by_lines(&context.input(2018, 1))
     .into_iter()
     .map(|modulation| modulation.parse::<i32>().unwrap())
     .collect();
 
-  let frequency: i32 = modulations.iter().sum();
+  if context.wants(1) {
+    let frequency: i32 = modulations.iter().sum();
 
-  println!("Resulting frequency is {}.", frequency);
-  println!(
-    "First repeating frequency is {}",
-    find_repeating_frequency(&modulations)
-  );
+    context.answer(1, "Resulting frequency is {}.", frequency);
+  }
+
+  if context.wants(2) {
+    context.answer(
+      2,
+      "First repeating frequency is {}",
+      find_repeating_frequency(&modulations),
+    );
+  }
 }
 
 fn find_repeating_frequency(modulations: &[i32]) -> i32 {
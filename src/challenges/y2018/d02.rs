@@ -1,12 +1,20 @@
 use std::collections::HashMap;
 
-use crate::util;
+use crate::util::{self, Context};
 
-pub fn run() {
-  let ids = util::read_file_by_lines(2018, 2);
+pub fn run(context: &mut Context) {
+  let ids = util::read_file_by_lines(&context.input(2018, 2));
 
-  println!("Checksum is {}.", checksum(&ids));
-  println!("Common letter between similar IDs are {}.", find_boxes(&ids));
+  if context.wants(1) {
+    context.answer(1, "Checksum is {}.", checksum(&ids));
+  }
+  if context.wants(2) {
+    context.answer(
+      2,
+      "Common letter between similar IDs are {}.",
+      find_boxes(&ids),
+    );
+  }
 }
 
 fn checksum(ids: &[String]) -> u32 {
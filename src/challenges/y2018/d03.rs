@@ -1,7 +1,7 @@
 use regex::Regex;
 use std::collections::HashSet;
 
-use crate::util;
+use crate::util::{self, Context};
 
 #[derive(Debug, PartialEq, Clone)]
 struct Fabric {
@@ -12,9 +12,9 @@ struct Fabric {
   y2: usize,
 }
 
-pub fn run() {
+pub fn run(context: &mut Context) {
   let rgx = Regex::new(r"#(\d+) @ (\d+),(\d+): (\d+)x(\d+)").unwrap();
-  let file = util::read_file_by_lines(2018, 3);
+  let file = util::read_file_by_lines(&context.input(2018, 3));
 
   let fabrics: Vec<Fabric> = file
     .iter()
@@ -46,13 +46,20 @@ pub fn run() {
     })
     .collect();
 
+  // The overlapping inches are needed by both parts
   let overlaps = find_overlapping_inches(&fabrics);
 
-  println!("There are {} inches of fabric overlapping.", overlaps.len());
+  context.answer(
+    1,
+    "There are {} inches of fabric overlapping.",
+    overlaps.len(),
+  );
 
-  let intact = find_overlapping_fabrics(&fabrics, &overlaps).unwrap();
+  if context.wants(2) {
+    let intact = find_overlapping_fabrics(&fabrics, &overlaps).unwrap();
 
-  println!("Claim not overlapping with any other: {}", intact.id);
+    context.answer(2, "Claim not overlapping with any other: {}", intact.id);
+  }
 }
 
 fn find_overlapping_inches(fabrics: &[Fabric]) -> HashSet<(usize, usize)> {
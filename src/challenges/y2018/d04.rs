@@ -2,14 +2,14 @@ use chrono::*;
 use regex::{Match, Regex};
 use std::collections::HashMap;
 
-use crate::util;
+use crate::util::{self, Context};
 
-pub fn run() {
+pub fn run(context: &mut Context) {
   let rgx =
     Regex::new(r"\[1518-\d+-(\d+) \d+:(\d+)\] Guard #(\d+) begins shift")
       .unwrap();
   let date_rgx = Regex::new(r"\[(1518-(\d+)-(\d+) \d+:(\d+))\] .*").unwrap();
-  let mut file = util::read_file_by_lines(2018, 4);
+  let mut file = util::read_file_by_lines(&context.input(2018, 4));
 
   file.sort_by(|line1, line2| {
     let line1_captures = date_rgx.captures(line1).unwrap();
@@ -53,68 +53,73 @@ pub fn run() {
       }
     }
   }
-  let (id, minutes) = timetable
-    .clone()
-    .into_iter()
-    .map(|(id, minutes)| {
-      (id, minutes.into_iter().flatten().collect::<Vec<_>>())
-    })
-    .max_by_key(|(_, minutes)| minutes.len())
-    .unwrap();
+  if context.wants(1) {
+    let (id, minutes) = timetable
+      .clone()
+      .into_iter()
+      .map(|(id, minutes)| {
+        (id, minutes.into_iter().flatten().collect::<Vec<_>>())
+      })
+      .max_by_key(|(_, minutes)| minutes.len())
+      .unwrap();
 
-  let (minute, _) = minutes
-    .iter()
-    .fold(HashMap::new(), |acc, minute| {
-      let mut acc = acc;
-      acc.entry(*minute).and_modify(|m| *m += 1).or_insert(1);
-      acc
-    })
-    .into_iter()
-    .max_by_key(|(_, minute)| *minute)
-    .unwrap();
+    let (minute, _) = minutes
+      .iter()
+      .fold(HashMap::new(), |acc, minute| {
+        let mut acc = acc;
+        acc.entry(*minute).and_modify(|m| *m += 1).or_insert(1);
+        acc
+      })
+      .into_iter()
+      .max_by_key(|(_, minute)| *minute)
+      .unwrap();
 
-  println!(
-    "Best guard is #{}, most asleep at minute {}: {}.",
-    id,
-    minute,
-    id * minute
-  );
-
-  let minutes: Vec<(u32, HashMap<u32, u32>)> = timetable
-    .into_iter()
-    .map(|(id, minutes)| {
-      (id, minutes.into_iter().flatten().collect::<Vec<_>>())
-    })
-    .map(|(id, minutes)| {
-      (
-        id,
-        minutes.iter().fold(HashMap::<u32, u32>::new(), |acc, minute| {
-          let mut acc = acc;
-          acc.entry(*minute).and_modify(|m| *m += 1).or_insert(1);
-          acc
-        }),
-      )
-    })
-    .collect();
-
-  let (mut id, mut minute, mut occurence) = (0, 0, 0);
-
-  for (guard, minutes) in &minutes {
-    for (min, count) in minutes {
-      if *count > occurence {
-        minute = *min;
-        id = *guard;
-        occurence = *count;
+    context.answer(
+      1,
+      &format!(
+        "Best guard is #{}, most asleep at minute {}: {{}}.",
+        id, minute
+      ),
+      id * minute,
+    );
+  }
+
+  if context.wants(2) {
+    let minutes: Vec<(u32, HashMap<u32, u32>)> = timetable
+      .into_iter()
+      .map(|(id, minutes)| {
+        (id, minutes.into_iter().flatten().collect::<Vec<_>>())
+      })
+      .map(|(id, minutes)| {
+        (
+          id,
+          minutes.iter().fold(HashMap::<u32, u32>::new(), |acc, minute| {
+            let mut acc = acc;
+            acc.entry(*minute).and_modify(|m| *m += 1).or_insert(1);
+            acc
+          }),
+        )
+      })
+      .collect();
+
+    let (mut id, mut minute, mut occurence) = (0, 0, 0);
+
+    for (guard, minutes) in &minutes {
+      for (min, count) in minutes {
+        if *count > occurence {
+          minute = *min;
+          id = *guard;
+          occurence = *count;
+        }
       }
     }
-  }
 
-  println!(
-    "Best guard is #{}, with best minute {}: {}.",
-    id,
-    minute,
-    id * minute
-  );
+    context.answer(
+      2,
+      &format!("Best guard is #{}, with best minute {}: {{}}.", id, minute),
+      id * minute,
+    );
+  }
 }
 
 fn cast_capture(capture: Option<Match>) -> u32 {
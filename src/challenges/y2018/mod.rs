@@ -3,7 +3,9 @@ mod d02;
 mod d03;
 mod d04;
 
-pub fn challenges() -> Vec<(&'static str, &'static dyn Fn())> {
+use super::Challenge;
+
+pub fn challenges() -> Vec<Challenge> {
   vec![
     ("Chronal Calibration", &d01::run),
     ("Inventory Management System", &d02::run),
@@ -1,21 +1,29 @@
-use crate::util;
+use crate::util::{self, Context};
 
-pub fn run() {
-  let file = util::read_file_by_lines(2019, 1);
+pub fn run(context: &mut Context) {
+  let file = util::read_file_by_lines(&context.input(2019, 1));
   let masses = file
     .iter()
     .filter(|line| !line.is_empty())
     .map(|line| line.parse::<i32>().expect("invalid input"));
 
-  let fuel_requirement: i32 =
-    masses.clone().map(|mass| get_fuel_requirement(mass, false)).sum();
+  if context.wants(1) {
+    let fuel_requirement: i32 =
+      masses.clone().map(|mass| get_fuel_requirement(mass, false)).sum();
 
-  println!("Fuel required to launch: {}", fuel_requirement);
+    context.answer(1, "Fuel required to launch: {}", fuel_requirement);
+  }
 
-  let fuel_requirement: i32 =
-    masses.map(|mass| get_fuel_requirement(mass, true)).sum();
+  if context.wants(2) {
+    let fuel_requirement: i32 =
+      masses.map(|mass| get_fuel_requirement(mass, true)).sum();
 
-  println!("Corrected fuel required to launch: {}", fuel_requirement)
+    context.answer(
+      2,
+      "Corrected fuel required to launch: {}",
+      fuel_requirement,
+    );
+  }
 }
 
 fn get_fuel_requirement(
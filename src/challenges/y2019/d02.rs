@@ -1,40 +1,76 @@
-use crate::{util, util::intcode::Program};
+use crate::util::{
+  intcode::{self, Limits, Program, Symbolic},
+  Context,
+};
 
-pub fn run() {
-  let file = util::read_split_file(2019, 2, ",");
-  let stack: Vec<i128> = file
-    .iter()
-    .map(|bit| bit.parse::<i128>().expect("invalid input"))
-    .collect();
+const TARGET: i128 = 19_690_720;
 
-  let mut program = Program::new(stack.clone(), vec![]);
+// Instructions the program can run symbolically before falling back to trying every pair
+const BUDGET: u64 = 100_000;
 
-  program.stack[1] = 12;
-  program.stack[2] = 2;
-  program.execute();
+pub fn run(context: &mut Context) {
+  let stack = intcode::load(context.input(2019, 2)).expect("invalid input");
 
-  println!("Gravity assist result: {}", program.stack[0]);
+  if context.wants(1) {
+    let mut program = Program::new(stack.clone(), vec![]);
+    program.limits = context.limits;
+
+    program.stack[1] = 12;
+    program.stack[2] = 2;
+    program.execute().expect("invalid program");
+
+    context.answer(1, "Gravity assist result: {}", program.stack[0]);
+  }
+
+  if context.wants(2) {
+    if let Some((noun, verb)) = parameters(&stack, context.limits) {
+      context.answer(2, "Gravity assist parameters: {}", 100 * noun + verb);
+    }
+  }
+}
+
+// Solve the program for its noun and verb, trying every pair when it cannot be solved symbolically
+fn parameters(stack: &[i128], limits: Limits) -> Option<(i128, i128)> {
+  let mut symbolic = Symbolic::new(stack);
+  symbolic.limits = Limits::instructions(BUDGET);
+  symbolic.bind(1, "noun");
+  symbolic.bind(2, "verb");
+
+  if symbolic.run().is_ok() {
+    let domains = [("noun", 0..=99), ("verb", 0..=99)];
+
+    if let Some(values) = intcode::solve(&symbolic.get(0), TARGET, &domains) {
+      return Some((values[0], values[1]));
+    }
+  }
 
   for noun in 0..=99 {
     for verb in 0..=99 {
-      let mut program = Program::new(stack.clone(), vec![]);
+      let mut program = Program::new(stack.to_vec(), vec![]);
+      program.limits = limits;
 
       program.stack[1] = noun;
       program.stack[2] = verb;
 
-      program.execute();
+      program.execute().expect("invalid program");
 
-      if program.stack[0] == 19_690_720 {
-        println!("Gravity assist parameters: {}", 100 * noun + verb);
-        return;
+      if program.stack[0] == TARGET {
+        return Some((noun, verb));
       }
     }
   }
+
+  None
 }
 
 #[cfg(test)]
 mod tests {
-  use crate::util::intcode::Program;
+  use super::{parameters, TARGET};
+  use crate::util::{
+    self,
+    intcode::{self, Limits, Program, Symbolic},
+  };
+
   #[test]
   fn part1() {
     let data = vec![
@@ -46,9 +82,30 @@ mod tests {
 
     for (stack, result) in data {
       let mut program = Program::new(stack, vec![]);
-      program.execute();
+      program.execute().expect("invalid program");
 
       assert_eq!(program.stack, result);
     }
   }
+
+  #[test]
+  fn part2() {
+    let stack = intcode::load(util::get_input(2019, 2)).unwrap();
+
+    // The program computes an affine combination of its noun and verb
+    let mut symbolic = Symbolic::new(&stack);
+    symbolic.bind(1, "noun");
+    symbolic.bind(2, "verb");
+    assert_eq!(symbolic.run(), Ok(()));
+    assert!(symbolic.get(0).affine().is_some());
+
+    let (noun, verb) = parameters(&stack, Limits::default()).unwrap();
+
+    let mut program = Program::new(stack, vec![]);
+    program.stack[1] = noun;
+    program.stack[2] = verb;
+    program.execute().expect("invalid program");
+
+    assert_eq!(program.stack[0], TARGET);
+  }
 }
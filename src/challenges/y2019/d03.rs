@@ -1,19 +1,26 @@
-use crate::util;
+use crate::util::{self, Context};
 use std::{
   cmp::Ordering,
   collections::{HashMap, HashSet},
 };
 
-pub fn run() {
-  let file = util::read_file_by_lines(2019, 3);
+pub fn run(context: &mut Context) {
+  let file = util::read_file_by_lines(&context.input(2019, 3));
   let paths: Vec<Vec<String>> = file
     .iter()
     .map(|line| line.split(',').map(|x| x.to_string()).collect())
     .collect();
 
   let intersections = get_intersections(paths);
-  println!("Closest intersection: {}", get_closest(intersections.clone()));
-  println!("Cheapest intersection: {}", get_cheapest(intersections));
+
+  if context.wants(1) {
+    let closest = get_closest(intersections.clone());
+    context.answer(1, "Closest intersection: {}", closest);
+  }
+  if context.wants(2) {
+    let cheapest = get_cheapest(intersections);
+    context.answer(2, "Cheapest intersection: {}", cheapest);
+  }
 }
 
 fn get_closest(intersections: Vec<(i64, i64)>) -> i64 {
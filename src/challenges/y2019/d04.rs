@@ -1,28 +1,33 @@
-use crate::util;
+use crate::util::{self, Context};
 
-pub fn run() {
-  let file = util::read_split_file(2019, 4, "-");
+pub fn run(context: &mut Context) {
+  let file = util::read_split_file(&context.input(2019, 4), "-");
   let range: Vec<u64> =
     file.iter().map(|x| x.parse::<u64>().expect("invalid input")).collect();
 
-  let mut matches = 0;
+  if context.wants(1) {
+    let mut matches = 0;
 
-  for password in range[0]..=range[1] {
-    if is_password_ok(password) {
-      matches += 1;
+    for password in range[0]..=range[1] {
+      if is_password_ok(password) {
+        matches += 1;
+      }
     }
+
+    context.answer(1, "Matches: {}", matches);
   }
 
-  println!("Matches: {}", matches);
-  let mut matches = 0;
+  if context.wants(2) {
+    let mut matches = 0;
 
-  for password in range[0]..=range[1] {
-    if is_password_really_ok(password) {
-      matches += 1;
+    for password in range[0]..=range[1] {
+      if is_password_really_ok(password) {
+        matches += 1;
+      }
     }
-  }
 
-  println!("Fixed matches: {}", matches);
+    context.answer(2, "Fixed matches: {}", matches);
+  }
 }
 
 fn is_password_ok(password: u64) -> bool {
@@ -1,15 +1,23 @@
-use crate::util::{self, intcode::Program};
+use crate::util::{
+  intcode::{self, ExitStatus, Program},
+  Context,
+};
 
-pub fn run() {
-  let file = util::read_split_file(2019, 5, ",");
-  let stack: Vec<i128> = file
-    .iter()
-    .map(|bit| bit.parse::<i128>().expect("invalid input"))
-    .collect();
+// Only the second part, the thermal radiator diagnostics, is solved
+pub fn run(context: &mut Context) {
+  if !context.wants(2) {
+    return;
+  }
+
+  let stack = intcode::load(context.input(2019, 5)).expect("invalid input");
 
   let mut program = Program::new(stack, vec![5]);
+  program.limits = context.limits;
 
-  println!("Result: {:?}", program.execute_for_output().unwrap());
+  match program.execute().expect("invalid program") {
+    ExitStatus::Output(result) => context.answer(2, "Result: {}", result),
+    ExitStatus::Halted => println!("The program did not output anything"),
+  }
 }
 
 #[cfg(test)]
@@ -59,7 +67,7 @@ mod tests {
     for (stack, input, ret) in data {
       let mut program = Program::new(stack, vec![input]);
 
-      assert_eq!(program.execute_for_output(), Some(ret));
+      assert_eq!(program.execute_for_output(), Ok(Some(ret)));
     }
   }
 }
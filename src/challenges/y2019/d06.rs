@@ -3,7 +3,7 @@ use std::{
   collections::{hash_map::Iter, HashMap},
 };
 
-use crate::util;
+use crate::util::{self, Context};
 
 #[derive(Debug, Default, Clone)]
 struct Planet {
@@ -40,8 +40,8 @@ impl Arena {
   }
 }
 
-pub fn run() {
-  let orbits = util::read_file_by_lines(2019, 6);
+pub fn run(context: &mut Context) {
+  let orbits = util::read_file_by_lines(&context.input(2019, 6));
   let mut list: HashMap<String, Vec<String>> = HashMap::new();
   let mut arena = Arena::default();
 
@@ -56,24 +56,29 @@ pub fn run() {
 
   find_orbiting_planets(&list, &mut arena, "COM".to_string());
 
-  let distance = arena
-    .iter()
-    .fold(0, |acc, (_, planet)| acc + find_distance(&arena, planet));
+  if context.wants(1) {
+    let distance = arena
+      .iter()
+      .fold(0, |acc, (_, planet)| acc + find_distance(&arena, planet));
 
-  println!("Global orbital distance: {}", distance);
+    context.answer(1, "Global orbital distance: {}", distance);
+  }
 
-  let mut you = find_path(&arena, arena.get("YOU").unwrap());
-  let mut san = find_path(&arena, arena.get("SAN").unwrap());
-  let min = cmp::min(you.len(), san.len());
-  for _ in 0..min {
-    if you[0] != san[0] {
-      break;
+  if context.wants(2) {
+    let mut you = find_path(&arena, arena.get("YOU").unwrap());
+    let mut san = find_path(&arena, arena.get("SAN").unwrap());
+    let min = cmp::min(you.len(), san.len());
+    for _ in 0..min {
+      if you[0] != san[0] {
+        break;
+      }
+      you.remove(0);
+      san.remove(0);
     }
-    you.remove(0);
-    san.remove(0);
-  }
 
-  println!("Distance between YOU and SAN: {}", you.len() + san.len());
+    let distance = you.len() + san.len();
+    context.answer(2, "Distance between YOU and SAN: {}", distance);
+  }
 }
 
 fn find_orbiting_planets(
@@ -1,78 +1,81 @@
 use itertools::Itertools;
 
-use crate::util::{self, intcode::Circuit};
-
-pub fn run() {
-  let file = util::read_split_file(2019, 7, ",");
-  let stack: Vec<i128> = file
-    .iter()
-    .map(|bit| bit.parse::<i128>().expect("invalid input"))
-    .collect();
-
-  let mut outputs: Vec<i128> = vec![];
-  for settings in (0..5).map(|_| (0..5)).multi_cartesian_product() {
-    if !settings.contains(&0)
-      || !settings.contains(&1)
-      || !settings.contains(&2)
-      || !settings.contains(&3)
-      || !settings.contains(&4)
-    {
-      continue;
+use crate::util::{
+  intcode::{self, Circuit, Engine, Limits},
+  Context,
+};
+
+pub fn run(context: &mut Context) {
+  let stack = intcode::load(context.input(2019, 7)).expect("invalid input");
+
+  if context.wants(1) {
+    let mut outputs: Vec<i128> = vec![];
+    for settings in (0..5).map(|_| (0..5)).multi_cartesian_product() {
+      if !settings.contains(&0)
+        || !settings.contains(&1)
+        || !settings.contains(&2)
+        || !settings.contains(&3)
+        || !settings.contains(&4)
+      {
+        continue;
+      }
+
+      outputs.push(amplify(stack.clone(), settings, context.limits));
     }
 
-    outputs.push(amplify(stack.clone(), settings));
+    context.answer(1, "Output signal: {}", outputs.iter().max().unwrap());
   }
 
-  println!("Output signal: {}", outputs.iter().max().unwrap());
-
-  let mut outputs: Vec<i128> = vec![];
-  for settings in (5..10).map(|_| (5..10)).multi_cartesian_product() {
-    if !settings.contains(&5)
-      || !settings.contains(&6)
-      || !settings.contains(&7)
-      || !settings.contains(&8)
-      || !settings.contains(&9)
-    {
-      continue;
+  if context.wants(2) {
+    let mut outputs: Vec<i128> = vec![];
+    for settings in (5..10).map(|_| (5..10)).multi_cartesian_product() {
+      if !settings.contains(&5)
+        || !settings.contains(&6)
+        || !settings.contains(&7)
+        || !settings.contains(&8)
+        || !settings.contains(&9)
+      {
+        continue;
+      }
+      outputs.push(amplify_with_feedback_loop(
+        stack.clone(),
+        settings,
+        context.limits,
+      ));
     }
-    outputs.push(amplify_with_feedback_loop(stack.clone(), settings));
-  }
 
-  println!(
-    "Output signal with feedback loop: {}",
-    outputs.iter().max().unwrap()
-  );
+    context.answer(
+      2,
+      "Output signal with feedback loop: {}",
+      outputs.iter().max().unwrap(),
+    );
+  }
 }
 
-fn amplify(stack: Vec<i128>, settings: Vec<i128>) -> i128 {
-  let mut circuit = Circuit::with_copies(
-    5,
-    stack,
-    |_, index, output| vec![settings[index], output],
-    false,
-  );
-  circuit.execute()
+fn amplify(stack: Vec<i128>, settings: Vec<i128>, limits: Limits) -> i128 {
+  let circuit =
+    Circuit::with_copies(5, stack, |index| vec![settings[index]], false)
+      .with_limits(limits)
+      .with_engine(Engine::Compiler);
+
+  circuit.execute(0).expect("invalid program")
 }
 
-fn amplify_with_feedback_loop(stack: Vec<i128>, settings: Vec<i128>) -> i128 {
-  let mut circuit = Circuit::with_copies(
-    5,
-    stack,
-    |cycle, index, output| {
-      if cycle == 0 {
-        vec![settings[index], output]
-      } else {
-        vec![output]
-      }
-    },
-    true,
-  );
+fn amplify_with_feedback_loop(
+  stack: Vec<i128>, settings: Vec<i128>, limits: Limits,
+) -> i128 {
+  let circuit =
+    Circuit::with_copies(5, stack, |index| vec![settings[index]], true)
+      .with_limits(limits)
+      .with_engine(Engine::Compiler);
 
-  circuit.execute()
+  circuit.execute(0).expect("invalid program")
 }
 
 #[cfg(test)]
 mod tests {
+  use crate::util::intcode::Limits;
+
   #[test]
   fn part1() {
     let data = vec![
@@ -100,7 +103,7 @@ mod tests {
     ];
 
     for (stack, settings, result) in data {
-      let output = super::amplify(stack, settings);
+      let output = super::amplify(stack, settings, Limits::default());
 
       assert_eq!(output, result);
     }
@@ -130,7 +133,8 @@ mod tests {
     ];
 
     for (stack, settings, result) in data {
-      let output = super::amplify_with_feedback_loop(stack, settings);
+      let output =
+        super::amplify_with_feedback_loop(stack, settings, Limits::default());
 
       assert_eq!(output, result);
     }
@@ -1,9 +1,9 @@
-use crate::util;
+use crate::util::{self, Context};
 
 const CANVAS_SIZE: (usize, usize) = (25, 6);
 
-pub fn run() {
-  let file = util::read_chared_file(2019, 8);
+pub fn run(context: &mut Context) {
+  let file = util::read_chared_file(&context.input(2019, 8));
   let pixels: Vec<u8> = file
     .iter()
     .map(|c| c.to_string().parse::<u8>().expect("invalid input"))
@@ -11,12 +11,18 @@ pub fn run() {
 
   let (width, height) = CANVAS_SIZE;
   let layers = get_layers(pixels, width, height);
-  let checksum = checksum(&layers);
 
-  println!("Image checksum: {}", checksum);
+  if context.wants(1) {
+    let checksum = checksum(&layers);
 
-  println!("Image render:");
-  render(&layers, width, height);
+    context.answer(1, "Image checksum: {}", checksum);
+  }
+
+  // The message is only printed, it is not checked
+  if context.shows(2) {
+    println!("Image render:");
+    render(&layers, width, height);
+  }
 }
 
 fn get_layers(raw: Vec<u8>, width: usize, height: usize) -> Vec<Vec<Vec<u8>>> {
@@ -1,16 +1,29 @@
-use crate::{util, util::intcode::Program};
-
-pub fn run() {
-  let file = util::read_split_file(2019, 9, ",");
-  let stack: Vec<i128> = file
-    .iter()
-    .map(|bit| bit.parse::<i128>().expect("invalid input"))
-    .collect();
-
-  let mut program = Program::new(stack.clone(), vec![1]);
-  println!("BOOST keycode: {}", program.execute_for_output().unwrap());
-  let mut program = Program::new(stack, vec![2]);
-  println!("BOOST distress signal: {}", program.execute_for_output().unwrap());
+use crate::util::{
+  intcode::{self, Program},
+  Context,
+};
+
+pub fn run(context: &mut Context) {
+  let stack = intcode::load(context.input(2019, 9)).expect("invalid input");
+
+  if context.wants(1) {
+    let mut program = Program::new(stack.clone(), vec![1]);
+    program.limits = context.limits;
+    context.answer(
+      1,
+      "BOOST keycode: {}",
+      program.execute_for_output().expect("invalid program").unwrap(),
+    );
+  }
+  if context.wants(2) {
+    let mut program = Program::new(stack, vec![2]);
+    program.limits = context.limits;
+    context.answer(
+      2,
+      "BOOST distress signal: {}",
+      program.execute_for_output().expect("invalid program").unwrap(),
+    );
+  }
 }
 
 #[cfg(test)]
@@ -23,17 +36,20 @@ mod tests {
       109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
     ];
     let mut program = Program::new(stack.clone(), vec![]);
-    program.execute();
+    program.execute().unwrap();
 
     assert_eq!(program.stack, stack);
 
     let mut program =
       Program::new(vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0], vec![]);
 
-    assert_ne!(program.execute_for_output().unwrap() / 1000000000000000, 0);
+    assert_ne!(
+      program.execute_for_output().unwrap().unwrap() / 1000000000000000,
+      0
+    );
 
     let mut program = Program::new(vec![104, 1125899906842624, 99], vec![]);
 
-    assert_eq!(program.execute_for_output().unwrap(), 1125899906842624);
+    assert_eq!(program.execute_for_output(), Ok(Some(1125899906842624)));
   }
 }
@@ -1,6 +1,6 @@
 use std::f64::EPSILON;
 
-use crate::util;
+use crate::util::{self, Context};
 
 type Coords = (isize, isize);
 type Angle = f64;
@@ -71,8 +71,8 @@ impl Asteroid {
   }
 }
 
-pub fn run() {
-  let file: Vec<Vec<char>> = util::read_file_by_lines(2019, 10)
+pub fn run(context: &mut Context) {
+  let file: Vec<Vec<char>> = util::read_file_by_lines(&context.input(2019, 10))
     .iter()
     .map(|line| line.chars().collect())
     .collect();
@@ -87,20 +87,27 @@ pub fn run() {
     }
   }
 
+  // The station is needed by both parts
   let (station, asteroids_in_sight) = best_monitoring_station(&asteroids);
 
-  println!("Best monitoring station: ({}, {})", station.x, station.y);
-  println!("Asteroids in sight: {}", asteroids_in_sight);
-
-  let asteroid =
-    search_and_destroy(&asteroids, (station.x, station.y)).unwrap();
-
-  println!(
-    "200th asteroid destroyed: ({}, {}) -> {}",
-    asteroid.x,
-    asteroid.y,
-    (asteroid.x * 100) + asteroid.y
-  );
+  if context.shows(1) {
+    println!("Best monitoring station: ({}, {})", station.x, station.y);
+  }
+  context.answer(1, "Asteroids in sight: {}", asteroids_in_sight);
+
+  if context.wants(2) {
+    let asteroid =
+      search_and_destroy(&asteroids, (station.x, station.y)).unwrap();
+
+    context.answer(
+      2,
+      &format!(
+        "200th asteroid destroyed: ({}, {}) -> {{}}",
+        asteroid.x, asteroid.y
+      ),
+      (asteroid.x * 100) + asteroid.y,
+    );
+  }
 }
 
 fn normalize_to_center(center: Coords, asteroids: &[Coords]) -> Vec<Asteroid> {
@@ -1,25 +1,31 @@
 use std::collections::HashMap;
 
-use crate::{util, util::intcode::Program};
+use crate::util::{
+  intcode::{self, Program},
+  Context,
+};
 
-pub fn run() {
-  let file = util::read_split_file(2019, 11, ",");
-  let stack: Vec<i128> = file
-    .iter()
-    .map(|bit| bit.parse::<i128>().expect("invalid input"))
-    .collect();
+pub fn run(context: &mut Context) {
+  let stack = intcode::load(context.input(2019, 11)).expect("invalid input");
 
-  let mut robot = PaintingRobot::new(stack.clone(), HashMap::default());
-  robot.boot();
+  if context.wants(1) {
+    let mut robot = PaintingRobot::new(stack.clone(), HashMap::default());
+    robot.program.limits = context.limits;
+    robot.boot();
 
-  println!("{} grid squares were painted", robot.painted_cells());
+    context.answer(1, "{} grid squares were painted", robot.painted_cells());
+  }
 
-  let mut grid = HashMap::new();
-  grid.insert((0, 0), Color::White);
+  // The registration identifier is only printed, it is not checked
+  if context.shows(2) {
+    let mut grid = HashMap::new();
+    grid.insert((0, 0), Color::White);
 
-  let mut robot = PaintingRobot::new(stack, grid);
-  robot.boot();
-  robot.report();
+    let mut robot = PaintingRobot::new(stack, grid);
+    robot.program.limits = context.limits;
+    robot.boot();
+    robot.report();
+  }
 }
 
 #[derive(Debug)]
@@ -42,24 +48,29 @@ impl PaintingRobot {
 
   fn boot(&mut self) {
     loop {
-      self.program.inputs =
-        vec![self.grid.get(&self.coords).unwrap_or(&Color::Black).value()];
+      let color = self.grid.get(&self.coords).unwrap_or(&Color::Black);
+      self.program.io.inputs.push_back(color.value());
 
-      let color = match self.program.execute_for_output() {
-        Some(0) => Color::Black,
-        Some(1) => Color::White,
+      // The robot paints and turns before asking for the color of its new position
+      self.program.run_until_blocked().expect("invalid program");
+
+      let outputs = &mut self.program.io.outputs;
+      let (color, turn) = match (outputs.pop_front(), outputs.pop_front()) {
+        (Some(color), Some(turn)) => (color, turn),
+        _ => break,
+      };
+
+      let color = match color {
+        0 => Color::Black,
+        1 => Color::White,
         _ => panic!("no color provided"),
       };
-      let turn = match self.program.execute_for_output() {
-        Some(0) => Turn::Left,
-        Some(1) => Turn::Right,
+      let turn = match turn {
+        0 => Turn::Left,
+        1 => Turn::Right,
         _ => panic!("no turn direction provided"),
       };
 
-      if self.program.halted {
-        break;
-      }
-
       self.advance(color, turn);
     }
   }
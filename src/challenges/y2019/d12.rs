@@ -7,7 +7,7 @@ use std::{
   slice::{Iter, IterMut},
 };
 
-use crate::util;
+use crate::util::{self, Context};
 
 const X: usize = 0;
 const Y: usize = 1;
@@ -154,9 +154,9 @@ impl<'a> Iterator for SystemGenerator<'a> {
   }
 }
 
-pub fn run() {
+pub fn run(context: &mut Context) {
   let rgx = Regex::new(r"<x=(.+), y=(.+), z=(.+)>").unwrap();
-  let file = util::read_file_by_lines(2019, 12);
+  let file = util::read_file_by_lines(&context.input(2019, 12));
 
   let mut system: System = file
     .iter()
@@ -185,11 +185,20 @@ pub fn run() {
     .collect::<Vec<Moon>>()
     .into();
 
-  let (_, system_1000th) = system.clone().generator().nth(1000).unwrap();
-  println!(
-    "Total systemic energy after 1000 steps: {}",
-    system_1000th.energy()
-  );
+  if context.wants(1) {
+    let (_, system_1000th) = system.clone().generator().nth(1000).unwrap();
+    context.answer(
+      1,
+      "Total systemic energy after 1000 steps: {}",
+      system_1000th.energy(),
+    );
+  }
 
-  println!("First epoch at which a state repeats: {}", system.full_circle_at());
+  if context.wants(2) {
+    context.answer(
+      2,
+      "First epoch at which a state repeats: {}",
+      system.full_circle_at(),
+    );
+  }
 }
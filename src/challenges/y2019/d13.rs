@@ -12,7 +12,10 @@ use termion::{
   raw::IntoRawMode,
 };
 
-use crate::{util, util::intcode::Program};
+use crate::util::{
+  intcode::{self, Paged, Program, Queue, RunState, Tracer},
+  Context,
+};
 
 type Coords = (u16, u16);
 type Scene = HashMap<Coords, Object>;
@@ -53,35 +56,49 @@ impl Display for Object {
   }
 }
 
-struct Game {
-  program: Program,
+struct Game<T = ()> {
+  // Inputs and outputs can be recorded with a session tracer, to be replayed later on
+  program: Program<Queue, Paged, T>,
   scene: Scene,
   paddle: Option<u16>,
+  ball: Option<u16>,
   score: i128,
   cursor: (u16, u16),
   print: bool,
 }
 
 impl Game {
-  fn new(stack: Vec<i128>) -> Game {
-    let mut stdout = stdout().into_raw_mode().unwrap();
+  fn new(stack: Vec<i128>, print: bool) -> Game {
+    Game::with_tracer(stack, print, ())
+  }
+}
+
+impl<T: Tracer> Game<T> {
+  fn with_tracer(stack: Vec<i128>, print: bool, tracer: T) -> Game<T> {
+    // The terminal is only needed when the game is printed
+    let cursor = if print {
+      let mut stdout = stdout().into_raw_mode().unwrap();
+      stdout.cursor_pos().unwrap()
+    } else {
+      (1, 1)
+    };
 
     Game {
-      program: Program::new(stack, vec![0]),
+      program: Program::new(stack, vec![]).with_tracer(tracer),
       scene: HashMap::new(),
       paddle: None,
+      ball: None,
       score: 0,
-      cursor: stdout.cursor_pos().unwrap(),
-      print: true,
+      cursor,
+      print,
     }
   }
 
   fn play(&mut self) {
-    while !self.program.halted {
+    while let Some((x, y, value)) = self.tick() {
       if self.print {
         thread::sleep(Duration::from_micros(500));
       }
-      let (x, y, value) = self.tick();
 
       if x == -1 && y == 0 {
         self.score = value;
@@ -93,15 +110,7 @@ impl Game {
       match object {
         Object::GameOver => break,
         Object::Paddle => self.paddle = Some(x as u16),
-        Object::Ball => {
-          if let Some(paddle) = self.paddle {
-            self.program.inputs = match paddle.cmp(&(x as u16)) {
-              Ordering::Greater => vec![-1],
-              Ordering::Less => vec![1],
-              Ordering::Equal => vec![0],
-            };
-          }
-        }
+        Object::Ball => self.ball = Some(x as u16),
         _ => (),
       }
       self.scene.insert((x as u16, y as u16), object);
@@ -116,12 +125,34 @@ impl Game {
     }
   }
 
-  fn tick(&mut self) -> (i128, i128, i128) {
-    (
-      self.program.execute_for_output().unwrap(),
-      self.program.execute_for_output().unwrap(),
-      self.program.execute_for_output().unwrap(),
-    )
+  // Run the program until it draws a tile, moving the joystick whenever the program asks for it
+  fn tick(&mut self) -> Option<(i128, i128, i128)> {
+    let mut tile = vec![];
+
+    while tile.len() < 3 {
+      match self.program.run_until_event().expect("invalid program") {
+        RunState::AwaitingInput => {
+          let joystick = self.joystick();
+          self.program.io.inputs.push_back(joystick);
+        }
+        RunState::Output(value) => tile.push(value),
+        _ => return None,
+      }
+    }
+
+    Some((tile[0], tile[1], tile[2]))
+  }
+
+  // Follow the ball with the paddle
+  fn joystick(&self) -> i128 {
+    match (self.paddle, self.ball) {
+      (Some(paddle), Some(ball)) => match paddle.cmp(&ball) {
+        Ordering::Greater => -1,
+        Ordering::Less => 1,
+        Ordering::Equal => 0,
+      },
+      _ => 0,
+    }
   }
 
   fn write_tile(&self, x: u16, y: u16, object: Object) {
@@ -142,23 +173,61 @@ impl Game {
   }
 }
 
-pub fn run() {
-  let stack: Vec<i128> = util::read_split_file(2019, 13, ",")
-    .iter()
-    .map(|bit| bit.parse::<i128>().expect("invalid stack"))
-    .collect();
+pub fn run(context: &mut Context) {
+  let stack = intcode::load(context.input(2019, 13)).expect("invalid input");
 
-  let mut game = Game::new(stack.clone());
-  game.print = false;
-  game.play();
+  if context.wants(1) {
+    let mut game = Game::new(stack.clone(), false);
+    game.program.limits = context.limits;
+    game.play();
 
-  let blocks =
-    game.scene.iter().filter(|(_, object)| **object == Object::Block).count();
+    let blocks =
+      game.scene.iter().filter(|(_, object)| **object == Object::Block).count();
 
-  println!("There are {} blocks on the screen.", blocks);
+    context.answer(1, "There are {} blocks on the screen.", blocks);
+  }
 
-  let mut game = Game::new(stack);
-  game.program.stack[0] = 2;
-  game.play();
-  println!("Game over! Final score is {}.", game.score);
+  if context.wants(2) {
+    let mut game = Game::new(stack, context.shows(2));
+    game.program.limits = context.limits;
+    game.program.stack[0] = 2;
+    game.play();
+    context.answer(2, "Game over! Final score is {}.", game.score);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Game;
+  use crate::util::{
+    self,
+    intcode::{self, Exchange, ReplayError, Session},
+  };
+
+  #[test]
+  fn replay() {
+    let mut stack =
+      intcode::load(util::get_input(2019, 13)).expect("invalid input");
+    stack[0] = 2;
+
+    let mut game = Game::with_tracer(stack.clone(), false, Session::new());
+    game.play();
+
+    let session = game.program.tracer;
+    assert_eq!(session.replay(stack.clone()), Ok(()));
+
+    // Moving the joystick differently changes the game
+    let mut diverging = session;
+    let joystick = diverging
+      .exchanges
+      .iter_mut()
+      .find_map(|exchange| match exchange {
+        Exchange::Input(value) => Some(value),
+        _ => None,
+      })
+      .unwrap();
+    *joystick = if *joystick == 0 { 1 } else { 0 };
+
+    assert!(matches!(diverging.replay(stack), Err(ReplayError::Output { .. })));
+  }
 }
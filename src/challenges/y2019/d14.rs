@@ -1,11 +1,11 @@
 use std::{cmp::Ordering, collections::HashMap};
 
-use crate::util;
+use crate::util::{self, Context};
 
 type Requirements = HashMap<String, (u128, Vec<(u128, String)>)>;
 
-pub fn run() {
-  let file = util::read_file_by_lines(2019, 14);
+pub fn run(context: &mut Context) {
+  let file = util::read_file_by_lines(&context.input(2019, 14));
   let raw: Vec<Vec<Vec<Vec<&str>>>> = file
     .iter()
     .map(|line| {
@@ -32,15 +32,21 @@ pub fn run() {
     requirements.insert(rm.to_owned(), (*rq, components));
   }
 
-  println!(
-    "We need {} ORES to produce 1 FUEL.",
-    get_ores_for(1, &requirements)
-  );
+  if context.wants(1) {
+    context.answer(
+      1,
+      "We need {} ORES to produce 1 FUEL.",
+      get_ores_for(1, &requirements),
+    );
+  }
 
-  println!(
-    "Fuel produced by one trillion ores: {}",
-    search_ores_for(1_000_000_000_000, &requirements)
-  );
+  if context.wants(2) {
+    context.answer(
+      2,
+      "Fuel produced by one trillion ores: {}",
+      search_ores_for(1_000_000_000_000, &requirements),
+    );
+  }
 }
 
 fn get_ores_for(fuel: u128, requirements: &Requirements) -> u128 {
@@ -1,6 +1,9 @@
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
 
-use crate::{util, util::intcode::Program};
+use crate::util::{
+  intcode::{self, Program, RunState},
+  Context,
+};
 
 #[derive(Copy, Clone)]
 enum Direction {
@@ -10,18 +13,6 @@ enum Direction {
   East,
 }
 
-impl From<Option<&i128>> for Direction {
-  fn from(direction: Option<&i128>) -> Direction {
-    match direction {
-      Some(1) => Direction::North,
-      Some(2) => Direction::South,
-      Some(3) => Direction::West,
-      Some(4) => Direction::East,
-      _ => panic!("invalid direction"),
-    }
-  }
-}
-
 impl Direction {
   fn all() -> [Direction; 4] {
     [Direction::North, Direction::South, Direction::West, Direction::East]
@@ -35,19 +26,9 @@ impl Direction {
       Direction::East => 4,
     }
   }
-
-  fn reverse(self) -> Direction {
-    match self {
-      Direction::North => Direction::South,
-      Direction::South => Direction::North,
-      Direction::West => Direction::East,
-      Direction::East => Direction::West,
-    }
-  }
 }
 
 struct RepairRobot {
-  position: (isize, isize),
   tank: (isize, isize),
   distance: u32,
   program: Program,
@@ -57,7 +38,6 @@ struct RepairRobot {
 impl RepairRobot {
   fn new(stack: Vec<i128>) -> RepairRobot {
     RepairRobot {
-      position: (0, 0),
       program: Program::new(stack, vec![]),
       tank: (0, 0),
       distance: 0,
@@ -65,93 +45,79 @@ impl RepairRobot {
     }
   }
 
+  // Explore the maze breadth-first, the droid being forked on every move instead of walking back from dead ends
   fn find_oxygen_tank(&mut self) {
-    let mut path: Vec<Direction> = vec![];
-    let mut distance = 0;
-
-    loop {
-      self.visited.insert(self.position);
+    let mut queue = VecDeque::new();
 
-      let mut moved = false;
-
-      if !self.program.inputs.is_empty() {
-        distance -= 1;
-        let next = match self.program.inputs.get(0).into() {
-          Direction::North => (self.position.0, self.position.1 - 1),
-          Direction::South => (self.position.0, self.position.1 + 1),
-          Direction::West => (self.position.0 - 1, self.position.1),
-          Direction::East => (self.position.0 + 1, self.position.1),
-        };
-
-        self.position = next;
-        self.program.execute();
-        continue;
-      }
+    self.visited.insert((0, 0));
+    queue.push_back(((0, 0), 0, self.program.clone()));
 
+    while let Some((position, distance, droid)) = queue.pop_front() {
       for direction in Direction::all().iter() {
-        let next = match direction {
-          Direction::North => (self.position.0, self.position.1 - 1),
-          Direction::South => (self.position.0, self.position.1 + 1),
-          Direction::West => (self.position.0 - 1, self.position.1),
-          Direction::East => (self.position.0 + 1, self.position.1),
-        };
+        let next = neighbour(position, *direction);
 
         if self.visited.contains(&next) {
           continue;
         }
 
-        self.program.inputs.push(direction.to_input());
-
-        match self.program.execute_for_output() {
-          Some(0) => continue,
-          Some(1) => {
-            distance += 1;
-            self.position = next;
+        let mut droid = droid.clone();
 
-            path.push(*direction);
-            moved = true;
-            break;
-          }
-
-          Some(2) => {
-            distance += 1;
-            self.tank = self.position;
-            self.distance = distance;
-            self.position = next;
-
-            path.push(*direction);
-            moved = true;
-            break;
+        match command(&mut droid, *direction) {
+          0 => continue,
+          1 => (),
+          2 => {
+            self.tank = position;
+            self.distance = distance + 1;
           }
           _ => panic!("unknown output"),
         }
-      }
 
-      if moved {
-        continue;
+        self.visited.insert(next);
+        queue.push_back((next, distance + 1, droid));
       }
+    }
+  }
+}
 
-      match path.pop() {
-        Some(direction) => {
-          self.program.inputs.push(direction.reverse().to_input())
-        }
-        _ => return,
+fn neighbour(position: (isize, isize), direction: Direction) -> (isize, isize) {
+  match direction {
+    Direction::North => (position.0, position.1 - 1),
+    Direction::South => (position.0, position.1 + 1),
+    Direction::West => (position.0 - 1, position.1),
+    Direction::East => (position.0 + 1, position.1),
+  }
+}
+
+// Send a movement command to the droid once it asks for one, and return its status report
+fn command(droid: &mut Program, direction: Direction) -> i128 {
+  loop {
+    match droid.run_until_event().expect("invalid program") {
+      RunState::AwaitingInput => {
+        droid.io.inputs.push_back(direction.to_input())
       }
+      RunState::Output(status) => return status,
+      _ => panic!("the droid stopped responding"),
     }
   }
 }
 
-pub fn run() {
-  let stack: Vec<i128> = util::read_split_file(2019, 15, ",")
-    .iter()
-    .map(|bit| bit.parse::<i128>().unwrap())
-    .collect();
+pub fn run(context: &mut Context) {
+  let stack = intcode::load(context.input(2019, 15)).expect("invalid input");
 
+  // The map explored by the droid is needed by both parts
   let mut robot = RepairRobot::new(stack);
+  robot.program.limits = context.limits;
   robot.find_oxygen_tank();
 
-  println!("Distance to oxygen tank: {}", robot.distance);
-  println!("Time taken to fill the ship with oxygen: {}", fill_ship(&robot));
+  context.answer(1, "Distance to oxygen tank: {}", robot.distance);
+
+  if context.wants(2) {
+    context.answer(
+      2,
+      "Time taken to fill the ship with oxygen: {}",
+      fill_ship(&robot),
+    );
+  }
 }
 
 fn fill_ship(robot: &RepairRobot) -> i32 {
@@ -164,12 +130,7 @@ fn fill_ship(robot: &RepairRobot) -> i32 {
     visited.insert(position);
 
     for direction in Direction::all().iter() {
-      let next = match direction {
-        Direction::North => (position.0, position.1 - 1),
-        Direction::South => (position.0, position.1 + 1),
-        Direction::West => (position.0 - 1, position.1),
-        Direction::East => (position.0 + 1, position.1),
-      };
+      let next = neighbour(position, *direction);
 
       if !robot.visited.contains(&next) || visited.contains(&next) {
         continue;
@@ -14,7 +14,9 @@ mod d13;
 mod d14;
 mod d15;
 
-pub fn challenges() -> Vec<(&'static str, &'static dyn Fn())> {
+use super::Challenge;
+
+pub fn challenges() -> Vec<Challenge> {
   vec![
     ("The Tyranny of the Rocket Equation", &d01::run),
     ("1202 Program Alarm", &d02::run),
@@ -0,0 +1,256 @@
+use std::{
+  collections::HashMap,
+  fs,
+  path::Path,
+  process,
+  time::{Duration, Instant},
+};
+
+use aoc2019::{
+  challenges::*,
+  util::{self, intcode::Limits, Context},
+};
+
+// Time any Intcode program of a challenge can run for, unless other limits are given
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+// Reasons for a challenge not to succeed, each exiting with its own code
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Failure {
+  Usage = 1,
+  NoSuchDay = 2,
+  MissingInput = 3,
+  WrongAnswer = 4,
+}
+
+impl Failure {
+  pub fn exit(self, message: &str) -> ! {
+    eprintln!("ERROR: {}", message);
+    process::exit(self as i32);
+  }
+}
+
+// Options shared by every subcommand running challenges
+#[derive(Debug, Clone)]
+pub struct Options {
+  pub part: Option<u8>,
+  pub input: Option<String>,
+  pub quiet: bool,
+  pub limits: Limits,
+}
+
+impl Default for Options {
+  fn default() -> Options {
+    Options {
+      part: None,
+      input: None,
+      quiet: false,
+      limits: Limits::duration(TIMEOUT),
+    }
+  }
+}
+
+impl Options {
+  // Whether any option differs from its default
+  pub fn given(&self) -> bool {
+    self.part.is_some()
+      || self.input.is_some()
+      || self.quiet
+      || self.limits != Options::default().limits
+  }
+}
+
+fn years() -> Vec<(u16, Vec<Challenge>)> {
+  vec![(2018, y2018::challenges()), (2019, y2019::challenges())]
+}
+
+fn year(year: &str) -> (u16, Vec<Challenge>) {
+  let years: HashMap<String, (u16, Vec<Challenge>)> = years()
+    .into_iter()
+    .map(|(year, challenges)| (year.to_string(), (year, challenges)))
+    .collect();
+
+  match years.get(year) {
+    Some(year) => year.clone(),
+    None => Failure::NoSuchDay.exit("the provided year was not found"),
+  }
+}
+
+// Days of a year selected by a day number or `all`, an input file only being given for a single day
+fn days(
+  challenges: &[Challenge], day: Option<&str>, options: &Options,
+) -> Vec<usize> {
+  let days = match day {
+    None | Some("all") => (1..=challenges.len()).collect(),
+    Some(day) => match day.parse::<usize>() {
+      Ok(day) if day >= 1 && day <= challenges.len() => vec![day],
+      Ok(_) => Failure::NoSuchDay.exit("no challenge for this day (yet)"),
+      Err(_) => Failure::Usage.exit(&format!("invalid day `{}`", day)),
+    },
+  };
+
+  if options.input.is_some() && days.len() > 1 {
+    Failure::Usage.exit("`--input` can only be given for a single day");
+  }
+
+  days
+}
+
+pub fn run(year: &str, day: &str, options: &Options) {
+  let (year, challenges) = self::year(year);
+  let days = days(&challenges, Some(day), options);
+  let mut failure = None;
+
+  for (index, day) in days.iter().enumerate() {
+    if index > 0 && !options.quiet {
+      println!();
+    }
+
+    if let Err((reason, message)) =
+      solve(year, *day, challenges[day - 1], options)
+    {
+      eprintln!("ERROR: {}", message);
+      failure = failure.or(Some(reason));
+    }
+  }
+
+  if days.len() > 1 && !options.quiet {
+    println!();
+    println!("Merry Christmas!");
+  }
+
+  if let Some(reason) = failure {
+    process::exit(reason as i32);
+  }
+}
+
+// Time every selected challenge, checking its answers without printing them
+pub fn bench(year: &str, day: Option<&str>, options: &Options) {
+  let (year, challenges) = self::year(year);
+  let options = Options { quiet: true, ..options.clone() };
+  let mut failure = None;
+
+  for day in days(&challenges, day, &options) {
+    let (name, _) = challenges[day - 1];
+    let start = Instant::now();
+    let result = solve(year, day, challenges[day - 1], &options);
+    let elapsed = start.elapsed();
+
+    let status = match &result {
+      Ok(()) => "ok",
+      Err((Failure::MissingInput, _)) => "no input",
+      Err(_) => "wrong",
+    };
+
+    println!(
+      "{} day {:0>2}  {:>10.3} ms  {:<8}  {}",
+      year,
+      day,
+      elapsed.as_secs_f64() * 1000.0,
+      status,
+      name
+    );
+
+    if let Err((reason, _)) = result {
+      failure = failure.or(Some(reason));
+    }
+  }
+
+  if let Some(reason) = failure {
+    process::exit(reason as i32);
+  }
+}
+
+// Every challenge, and whether its input and expected answers are available
+pub fn list() {
+  for (year, challenges) in years() {
+    println!("{}", year);
+
+    for (index, (name, _)) in challenges.iter().enumerate() {
+      let day = index as u8 + 1;
+      let input = Path::new(&util::input_path(year, day)).exists();
+      let answers = Path::new(&util::answers_path(year, day)).exists();
+
+      let status = match (input, answers) {
+        (false, _) => "no input",
+        (true, false) => "unchecked",
+        (true, true) => "",
+      };
+
+      let line = format!("  {:>2}  {:<40}  {}", day, name, status);
+      println!("{}", line.trim_end());
+    }
+  }
+}
+
+// Run one challenge and compare its answers to the expected ones, when known
+fn solve(
+  year: u16, day: usize, (name, challenge): Challenge, options: &Options,
+) -> Result<(), (Failure, String)> {
+  let day = day as u8;
+  let mut context = Context {
+    part: options.part,
+    quiet: options.quiet,
+    input: options.input.clone(),
+    limits: options.limits,
+    ..Context::new()
+  };
+
+  let path = context.input(year, day);
+
+  if !Path::new(&path).exists() {
+    return Err((
+      Failure::MissingInput,
+      format!("input file `{}` does not exist", path),
+    ));
+  }
+
+  if !options.quiet {
+    println!("# DAY {} - {}", day, name);
+  }
+
+  challenge(&mut context);
+
+  // Expected answers only hold for the input of the challenge
+  if options.input.is_some() {
+    return Ok(());
+  }
+
+  let mut expected: Vec<(u8, String)> = expected(year, day)
+    .into_iter()
+    .filter(|(part, _)| context.wants(*part))
+    .collect();
+  expected.sort();
+
+  // A wanted part without any answer is as wrong as a different answer
+  for (part, expected) in expected {
+    let message = match context.answers.iter().find(|(p, _)| *p == part) {
+      Some((_, found)) if *found == expected => continue,
+      Some((_, found)) => format!(
+        "wrong answer to part {} of {} day {}: expected {}, found {}",
+        part, year, day, expected, found
+      ),
+      None => format!(
+        "no answer to part {} of {} day {}: expected {}",
+        part, year, day, expected
+      ),
+    };
+
+    return Err((Failure::WrongAnswer, message));
+  }
+
+  Ok(())
+}
+
+fn expected(year: u16, day: u8) -> HashMap<u8, String> {
+  let answers = fs::read_to_string(util::answers_path(year, day));
+
+  answers
+    .unwrap_or_default()
+    .lines()
+    .filter_map(|line| line.trim().split_once(' '))
+    .filter_map(|(part, answer)| {
+      Some((part.parse().ok()?, answer.trim().to_string()))
+    })
+    .collect()
+}
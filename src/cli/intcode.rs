@@ -0,0 +1,280 @@
+use std::{
+  fs::{self, File},
+  io::{self, BufRead, BufWriter, Cursor, Read, Write},
+  time::Duration,
+};
+
+use aoc2019::util::intcode::{
+  self, Ascii, Debugger, Fuzzer, IntcodeIo, Limits, Paged, Profiler, Program,
+  RunState, Session, Stream, TraceWriter, Tracer,
+};
+
+use crate::fatal;
+
+const USAGE: &str = "Usage: aoc2019 intcode <asm|debug|disasm> <FILE>
+       aoc2019 intcode analyze <FILE> [--dot]
+       aoc2019 intcode fuzz [--seed <SEED>] [--iterations <COUNT>]
+       aoc2019 intcode replay <FILE> <SESSION>
+       aoc2019 intcode run [OPTIONS] <FILE> [INPUT...]
+
+Options for run:
+  --ascii                     exchange lines of text with the program
+  --trace <PATH>              write a trace of every executed instruction
+  --profile                   print where the program spent its time
+  --folded <PATH>             write pseudo call stacks for flamegraph tools
+  --record <PATH>             write every input and output to a session file
+  --max-instructions <COUNT>  stop after COUNT instructions
+  --timeout <SECONDS>         stop after SECONDS seconds";
+
+pub fn run(args: &[String]) {
+  match (args.first().map(String::as_str), args.get(1)) {
+    (Some("-h"), _) | (Some("--help"), _) => println!("{}", USAGE),
+    (Some("fuzz"), _) => fuzz(&args[1..]),
+    (Some("analyze"), Some(path)) => analyze(path, &args[2..]),
+    (Some("asm"), Some(path)) => asm(path),
+    (Some("debug"), Some(path)) => debug(path),
+    (Some("disasm"), Some(path)) => disasm(path),
+    (Some("replay"), Some(path)) => match args.get(2) {
+      Some(session) => replay(path, session),
+      None => fatal(USAGE),
+    },
+    (Some("run"), Some(_)) => run_image(&args[1..]),
+    _ => fatal(USAGE),
+  }
+}
+
+fn read_image(path: &str) -> Vec<i128> {
+  match intcode::load(path) {
+    Ok(image) => image,
+    Err(err) => fatal(&err.to_string()),
+  }
+}
+
+// Print a report of the static analysis of an image, or its control-flow graph
+fn analyze(path: &str, args: &[String]) {
+  let analysis = intcode::analyze(&read_image(path));
+
+  match args {
+    [] => print!("{}", analysis),
+    [flag] if flag == "--dot" => print!("{}", analysis.dot()),
+    _ => fatal(USAGE),
+  }
+}
+
+// Run random programs under every execution engine until they disagree, printing a minimized reproducer
+fn fuzz(args: &[String]) {
+  let mut seed = 0;
+  let mut iterations = 10_000;
+  let mut args = args.iter();
+
+  while let Some(arg) = args.next() {
+    match (arg.as_str(), args.next().map(|value| value.parse())) {
+      ("--seed", Some(Ok(value))) => seed = value,
+      ("--iterations", Some(Ok(value))) => iterations = value as usize,
+      _ => fatal(USAGE),
+    }
+  }
+
+  match Fuzzer::new(seed).run(iterations) {
+    Ok(()) => println!("{} programs, no divergence", iterations),
+    Err(divergence) => {
+      print!("{}", divergence);
+      fatal("engines diverged");
+    }
+  }
+}
+
+fn asm(path: &str) {
+  let source = match fs::read_to_string(path) {
+    Ok(source) => source,
+    Err(_) => fatal("could not read the assembly source"),
+  };
+
+  match intcode::assemble(&source) {
+    Ok(image) => {
+      let words: Vec<String> =
+        image.iter().map(|word| word.to_string()).collect();
+      println!("{}", words.join(","));
+    }
+    Err(err) => fatal(&err.to_string()),
+  }
+}
+
+fn debug(path: &str) {
+  let mut debugger = Debugger::new(Program::new(read_image(path), vec![]));
+  let stdin = io::stdin();
+
+  if debugger.repl(stdin.lock(), io::stdout()).is_err() {
+    fatal("could not interact with the terminal");
+  }
+}
+
+fn disasm(path: &str) {
+  for line in intcode::disassemble(&read_image(path)) {
+    println!("{}", line);
+  }
+}
+
+// Check that an image still behaves as recorded in a session
+fn replay(path: &str, session: &str) {
+  let session = match Session::load(session) {
+    Ok(session) => session,
+    Err(err) => fatal(&format!("could not load the session: {}", err)),
+  };
+
+  match session.replay(read_image(path)) {
+    Ok(()) => println!("{} exchanges replayed", session.exchanges.len()),
+    Err(err) => fatal(&err.to_string()),
+  }
+}
+
+// Run an image with the provided inputs, then with the ones read from stdin, printing its outputs
+// In ASCII mode, every input is a line of text
+// Options can be given anywhere, the first other argument being the image and the next ones its inputs
+fn run_image(args: &[String]) {
+  let mut inputs = vec![];
+  let mut trace = None;
+  let mut profile = false;
+  let mut folded = None;
+  let mut record = None;
+  let mut limits = Limits::default();
+  let mut ascii = false;
+  let mut args = args.iter();
+
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "--ascii" => ascii = true,
+      "--trace" => match args.next() {
+        Some(path) => trace = Some(path),
+        None => fatal(USAGE),
+      },
+      "--profile" => profile = true,
+      "--folded" => match args.next() {
+        Some(path) => folded = Some(path),
+        None => fatal(USAGE),
+      },
+      "--record" => match args.next() {
+        Some(path) => record = Some(path),
+        None => fatal(USAGE),
+      },
+      "--max-instructions" => match args.next().map(|count| count.parse()) {
+        Some(Ok(count)) => limits.instructions = Some(count),
+        _ => fatal(USAGE),
+      },
+      "--timeout" => {
+        let seconds = args.next().and_then(|seconds| seconds.parse().ok());
+        match seconds.map(Duration::try_from_secs_f64) {
+          Some(Ok(duration)) => limits.duration = Some(duration),
+          _ => fatal(USAGE),
+        }
+      }
+      input => inputs.push(input.to_string()),
+    }
+  }
+
+  if inputs.is_empty() {
+    fatal(USAGE);
+  }
+  let path = inputs.remove(0);
+
+  let trace = trace.map(|path| match File::create(path) {
+    Ok(file) => TraceWriter::new(BufWriter::new(file)),
+    Err(_) => fatal("could not create the trace file"),
+  });
+  let profiler = (profile || folded.is_some()).then(Profiler::new);
+  let session = record.map(|_| Session::new());
+  let tracer = (trace, (profiler, session));
+  let image = read_image(&path);
+  let stdin = io::stdin();
+
+  let ((trace, (profiler, session)), error) = if ascii {
+    let mut program = Program::new(image, vec![]).with_tracer(tracer);
+    program.limits = limits;
+
+    converse(Ascii::new(program), &inputs, stdin.lock())
+  } else {
+    let reader = Cursor::new(inputs.join("\n") + "\n").chain(stdin.lock());
+    let mut program =
+      Program::with_io(image, Stream::new(reader, io::stdout()))
+        .with_tracer(tracer);
+    program.limits = limits;
+
+    execute(program)
+  };
+
+  // The trace has to be flushed before exiting
+  drop(trace);
+
+  if let Some(profiler) = profiler {
+    if profile {
+      eprint!("{}", profiler);
+    }
+
+    if let Some(path) = folded {
+      let written = File::create(path)
+        .and_then(|file| profiler.write_folded(BufWriter::new(file)));
+      if written.is_err() {
+        fatal("could not write the folded stacks");
+      }
+    }
+  }
+
+  // The session is kept even if the program failed, to reproduce the failure
+  if let (Some(session), Some(path)) = (session, record) {
+    if session.save(path).is_err() {
+      fatal("could not write the session");
+    }
+  }
+
+  if let Some(error) = error {
+    fatal(&error);
+  }
+}
+
+// Run a program until it halts, handing its tracer back with the error that stopped it, if any
+fn execute<IO: IntcodeIo, T: Tracer>(
+  mut program: Program<IO, Paged, T>,
+) -> (T, Option<String>) {
+  let error = match program.run_until_blocked() {
+    Ok(RunState::Halted) => None,
+    Ok(_) => Some("the program is missing inputs".to_string()),
+    Err(err) => Some(err.to_string()),
+  };
+
+  (program.tracer, error)
+}
+
+// Print the text of the program, and answer with lines from `reader` whenever it waits for some
+fn converse<T: Tracer, R: BufRead>(
+  mut ascii: Ascii<Paged, T>, lines: &[String], reader: R,
+) -> (T, Option<String>) {
+  let mut reader = reader.lines();
+
+  for line in lines {
+    ascii.send_line(line);
+  }
+
+  let error = loop {
+    match ascii.run() {
+      Ok(text) => print!("{}", text),
+      Err(err) => break Some(err.to_string()),
+    }
+
+    if ascii.halted() {
+      break None;
+    }
+
+    let _ = io::stdout().flush();
+
+    match reader.next() {
+      Some(Ok(line)) => ascii.send_line(&line),
+      _ => break Some("the program is missing inputs".to_string()),
+    }
+  };
+
+  for answer in &ascii.answers {
+    println!("{}", answer);
+  }
+
+  (ascii.program.tracer, error)
+}
@@ -0,0 +1,2 @@
+pub mod challenges;
+pub mod intcode;
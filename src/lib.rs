@@ -0,0 +1,5 @@
+#![feature(pattern, vec_remove_item)]
+#![cfg_attr(test, feature(test))]
+
+pub mod challenges;
+pub mod util;
@@ -1,60 +1,102 @@
-#![feature(pattern, vec_remove_item)]
+mod cli;
 
-mod challenges;
-mod util;
+use std::{env, time::Duration};
 
-use std::{collections::HashMap, env, process};
+use cli::challenges::{self, Failure, Options};
 
-use crate::challenges::*;
+const USAGE: &str = "Usage: aoc2019 [OPTIONS] <COMMAND>
 
-fn main() {
-  let mut challenges =
-    HashMap::<&str, Vec<(&'static str, &'static dyn Fn())>>::new();
-
-  challenges.insert("2018", y2018::challenges());
-  challenges.insert("2019", y2019::challenges());
-
-  let args: Vec<String> = env::args().collect();
-
-  if let (Some(year), Some(day)) = (args.get(1), args.get(2)) {
-    if let Some(challenges) = challenges.get(year.as_str()) {
-      if let Ok(day) = day.parse::<usize>() {
-        run(&challenges, day);
-      } else if day == "all" {
-        run_all(&challenges);
-      } else {
-        fatal("Usage: aoc2019 <YEAR> <DAY|all>")
-      }
-    } else {
-      fatal("the provided year was not found")
-    }
-  } else {
-    fatal("Usage: aoc2019 <YEAR> <DAY|all>")
-  }
-}
+Commands:
+  run <YEAR> <DAY|all> [--part 1|2]  run challenges, or only one of their
+                                     parts, and check their answers
+  list                               list every challenge and its input
+  bench <YEAR> [DAY]                 time challenges and check their answers
+  intcode <SUBCOMMAND>               Intcode tools, see `aoc2019 intcode --help`
+  <YEAR> <DAY|all>                   same as `run`
 
-fn run(challenges: &[(&str, &dyn Fn())], day: usize) {
-  if day < 1 || day > challenges.len() {
-    fatal("no challenge for this day (yet)");
-  }
+Options:
+  --input <PATH>  read the input of a single day from PATH instead of
+                  inputs/<YEAR>/dNN.txt
+  --quiet         only print errors
+  --max-instructions <COUNT>
+                  stop any Intcode program after COUNT instructions
+  --timeout <SECONDS>
+                  stop any Intcode program after SECONDS seconds (60 by default)
+  -h, --help      print this message
 
-  let (name, challenge) = challenges[day - 1];
+Exit codes:
+  1  invalid usage
+  2  no such year or day
+  3  input missing
+  4  wrong answer";
 
-  println!("# DAY {} - {}", day, name);
+fn main() {
+  let args: Vec<String> = env::args().skip(1).collect();
+  let (options, command) = parse(&args);
 
-  challenge();
+  match command.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+    // Intcode tools take their own options
+    ["intcode", ..] if !options.given() => cli::intcode::run(&command[1..]),
+    ["intcode", ..] => fatal(USAGE),
+    ["list"] if options.part.is_none() => challenges::list(),
+    ["bench", year] if options.part.is_none() => {
+      challenges::bench(year, None, &options)
+    }
+    ["bench", year, day] if options.part.is_none() => {
+      challenges::bench(year, Some(day), &options)
+    }
+    ["run", year, day] => challenges::run(year, day, &options),
+    // Positional form of the first versions
+    [year, day] if *year != "bench" => challenges::run(year, day, &options),
+    _ => fatal(USAGE),
+  }
 }
 
-fn run_all(challenges: &[(&str, &dyn Fn())]) {
-  for day in 1..=challenges.len() {
-    run(challenges, day);
-    println!();
+// Split global options from the words of the command, wherever they appear
+fn parse(args: &[String]) -> (Options, Vec<String>) {
+  let mut options = Options::default();
+  let mut command = vec![];
+  let mut args = args.iter();
+
+  while let Some(arg) = args.next() {
+    match arg.as_str() {
+      "-h" | "--help" => {
+        println!("{}", USAGE);
+        std::process::exit(0);
+      }
+      "--quiet" => options.quiet = true,
+      "--input" => match args.next() {
+        Some(path) => options.input = Some(path.clone()),
+        None => fatal(USAGE),
+      },
+      "--max-instructions" => match args.next().map(|count| count.parse()) {
+        Some(Ok(count)) => options.limits.instructions = Some(count),
+        _ => fatal(USAGE),
+      },
+      "--timeout" => {
+        let seconds = args.next().and_then(|seconds| seconds.parse().ok());
+        match seconds.map(Duration::try_from_secs_f64) {
+          Some(Ok(duration)) => options.limits.duration = Some(duration),
+          _ => fatal(USAGE),
+        }
+      }
+      "--part" => match args.next().map(String::as_str) {
+        Some("1") => options.part = Some(1),
+        Some("2") => options.part = Some(2),
+        _ => fatal(USAGE),
+      },
+      // Everything following `intcode` belongs to the subcommand
+      "intcode" if command.is_empty() => {
+        command.push(arg.clone());
+        command.extend(args.by_ref().cloned());
+      }
+      _ => command.push(arg.clone()),
+    }
   }
 
-  println!("Merry Christmas!");
+  (options, command)
 }
 
-fn fatal(message: &str) {
-  eprintln!("ERROR: {}", message);
-  process::exit(1);
+fn fatal(message: &str) -> ! {
+  Failure::Usage.exit(message)
 }
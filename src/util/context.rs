@@ -0,0 +1,70 @@
+use std::fmt::Display;
+
+use super::{intcode::Limits, io::input_path};
+
+// Settings a challenge is run with, and the answers it gave
+//  * `part` is the only part to solve, both being solved by default
+//  * `quiet` records the answers without printing them
+//  * `input` is a file read instead of the input of the challenge
+//  * `limits` bound every Intcode program run by the challenge
+#[derive(Debug, Default, Clone)]
+pub struct Context {
+  pub part: Option<u8>,
+  pub quiet: bool,
+  pub input: Option<String>,
+  pub limits: Limits,
+  pub answers: Vec<(u8, String)>,
+}
+
+impl Context {
+  pub fn new() -> Context {
+    Context::default()
+  }
+
+  // Path of the input file of a challenge
+  pub fn input(&self, year: u16, day: u8) -> String {
+    match &self.input {
+      Some(path) => path.clone(),
+      None => input_path(year, day),
+    }
+  }
+
+  // Whether a part should be solved
+  pub fn wants(&self, part: u8) -> bool {
+    self.part.is_none_or(|wanted| wanted == part)
+  }
+
+  // Whether anything about a part should be printed
+  pub fn shows(&self, part: u8) -> bool {
+    self.wants(part) && !self.quiet
+  }
+
+  // Record the answer to a part, printing `text` with its `{}` replaced by the answer
+  pub fn answer(&mut self, part: u8, text: &str, value: impl Display) {
+    let value = value.to_string();
+
+    if self.shows(part) {
+      println!("{}", text.replacen("{}", &value, 1));
+    }
+    if self.wants(part) {
+      self.answers.push((part, value));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Context;
+
+  #[test]
+  fn answers() {
+    let mut context = Context { part: Some(2), quiet: true, ..Context::new() };
+
+    context.answer(1, "First: {}", 12);
+    context.answer(2, "Second: {}", "abc");
+
+    assert!(!context.wants(1));
+    assert_eq!(context.answers, vec![(2, String::from("abc"))]);
+    assert_eq!(context.input(2019, 1), "inputs/2019/d01.txt");
+  }
+}
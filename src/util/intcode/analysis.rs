@@ -0,0 +1,395 @@
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  convert::TryFrom,
+  fmt,
+  ops::Range,
+};
+
+use super::{
+  disassemble_at, instruction::Instruction, mode::Mode, opcode::Opcode,
+  Extensions,
+};
+
+// A run of instructions only entered from its first one, and only left from its last one
+//  * `successors` holds the start of the blocks control can be transferred to
+//  * `indirect` is set when the block ends with a jump to an address read from memory
+#[derive(Debug, PartialEq, Clone)]
+pub struct Block {
+  pub start: usize,
+  pub end: usize,
+  pub successors: Vec<usize>,
+  pub indirect: bool,
+}
+
+// Result of the static analysis of an image
+//  * `indirect` holds the address of every jump whose target is read from memory
+//  * `writes` holds the instructions writing into code (by address), with the address they write to
+//  * `unreached` holds the ranges of words no instruction was found to reach
+#[derive(Debug, Clone)]
+pub struct Analysis {
+  pub blocks: Vec<Block>,
+  pub indirect: Vec<usize>,
+  pub writes: Vec<(usize, usize)>,
+  pub unreached: Vec<Range<usize>>,
+  image: Vec<i128>,
+}
+
+// Where control can go after an instruction
+struct Flow {
+  targets: Vec<usize>,
+  next: Option<usize>,
+  indirect: bool,
+}
+
+// Follow every path from the first instruction of an image, without executing anything
+// Targets of indirect jumps are unknown, so constants computed from immediate operands (such as return addresses
+// pushed before a call) are assumed to be possible targets, as long as an instruction can be decoded there
+pub fn analyze(image: &[i128]) -> Analysis {
+  let extensions = Extensions::new();
+  let read = |address: usize| image.get(address).copied().unwrap_or_default();
+  let decode =
+    |address: usize| match Instruction::decode(address, read, &extensions) {
+      Ok(instruction)
+        if instruction
+          .next(address)
+          .is_some_and(|next| next <= image.len()) =>
+      {
+        Some(instruction)
+      }
+      _ => None,
+    };
+
+  let mut code: BTreeMap<usize, Instruction> = BTreeMap::new();
+  let mut leaders = BTreeSet::new();
+  let mut constants = BTreeSet::new();
+  let mut indirect = BTreeSet::new();
+  let mut pending = vec![0];
+
+  leaders.insert(0);
+
+  loop {
+    while let Some(address) = pending.pop() {
+      if code.contains_key(&address) {
+        continue;
+      }
+
+      // Execution would fail on a word that cannot be decoded, the path stops there
+      let instruction = match decode(address) {
+        Some(instruction) => instruction,
+        None => continue,
+      };
+
+      code.insert(address, instruction);
+
+      if let Some(value) = constant(&instruction) {
+        constants.insert(value);
+      }
+
+      let flow = flow(address, &instruction);
+      if flow.indirect {
+        indirect.insert(address);
+      }
+      for target in flow.targets {
+        leaders.insert(target);
+        pending.push(target);
+      }
+      pending.extend(flow.next);
+    }
+
+    if indirect.is_empty() {
+      break;
+    }
+
+    let candidates: Vec<usize> = constants
+      .iter()
+      .filter_map(|value| usize::try_from(*value).ok())
+      .filter(|address| !covers(&code, *address))
+      .filter(|address| decode(*address).is_some())
+      .collect();
+
+    if candidates.is_empty() {
+      break;
+    }
+
+    leaders.extend(candidates.iter().copied());
+    pending.extend(candidates);
+  }
+
+  let blocks = blocks(&code, &leaders);
+  let covered: BTreeSet<usize> = code
+    .iter()
+    .flat_map(|(address, instruction)| *address..address + instruction.size())
+    .collect();
+
+  Analysis {
+    blocks,
+    indirect: indirect.into_iter().collect(),
+    writes: writes(&code, &covered),
+    unreached: unreached(image.len(), &covered),
+    image: image.to_vec(),
+  }
+}
+
+fn flow(address: usize, instruction: &Instruction) -> Flow {
+  let next = address + instruction.size();
+  let [condition, target, _] = instruction.params;
+
+  match instruction.op {
+    Opcode::Halt(..) => Flow { targets: vec![], next: None, indirect: false },
+    Opcode::JumpIf(m1, m2, _) | Opcode::JumpUnless(m1, m2, _) => {
+      let expected = matches!(instruction.op, Opcode::JumpIf(..));
+
+      // With an immediate condition, the jump is either always or never taken
+      let (jumps, falls) = match m1 {
+        Mode::Immediate if (condition != 0) == expected => (true, false),
+        Mode::Immediate => (false, true),
+        _ => (true, true),
+      };
+
+      let direct = matches!(m2, Mode::Immediate);
+      let targets = match usize::try_from(target) {
+        Ok(target) if jumps && direct => vec![target],
+        _ => vec![],
+      };
+
+      Flow { targets, next: falls.then_some(next), indirect: jumps && !direct }
+    }
+    _ => Flow { targets: vec![], next: Some(next), indirect: false },
+  }
+}
+
+// Whether `address` is part of an instruction that was already reached
+fn covers(code: &BTreeMap<usize, Instruction>, address: usize) -> bool {
+  match code.range(..=address).next_back() {
+    Some((start, instruction)) => start + instruction.size() > address,
+    None => false,
+  }
+}
+
+// Value computed by an instruction only from immediate operands
+fn constant(instruction: &Instruction) -> Option<i128> {
+  let [v1, v2, _] = instruction.params;
+
+  match instruction.op {
+    Opcode::Add(Mode::Immediate, Mode::Immediate, _) => v1.checked_add(v2),
+    Opcode::Mul(Mode::Immediate, Mode::Immediate, _) => v1.checked_mul(v2),
+    _ => None,
+  }
+}
+
+// Split reached instructions into blocks, a new one starting at every leader and after every jump
+fn blocks(
+  code: &BTreeMap<usize, Instruction>, leaders: &BTreeSet<usize>,
+) -> Vec<Block> {
+  let mut blocks = vec![];
+  let mut start = None;
+
+  for (address, instruction) in code {
+    let first = *start.get_or_insert(*address);
+    let next = address + instruction.size();
+    let flow = flow(*address, instruction);
+
+    let ends = !flow.targets.is_empty()
+      || flow.next != Some(next)
+      || flow.indirect
+      || leaders.contains(&next)
+      || !code.contains_key(&next);
+
+    if ends {
+      let mut successors = flow.targets;
+      successors.extend(flow.next.filter(|next| code.contains_key(next)));
+      successors.sort_unstable();
+      successors.dedup();
+
+      blocks.push(Block {
+        start: first,
+        end: next,
+        successors,
+        indirect: flow.indirect,
+      });
+      start = None;
+    }
+  }
+
+  blocks
+}
+
+// Instructions writing, in position mode, to a word that is part of a reached instruction
+// Writes in relative mode depend on the relative base, and are not reported
+fn writes(
+  code: &BTreeMap<usize, Instruction>, covered: &BTreeSet<usize>,
+) -> Vec<(usize, usize)> {
+  code
+    .iter()
+    .filter_map(|(address, instruction)| {
+      let index = instruction.op.target()?;
+      let mode = instruction.op.modes()[index];
+      let target = usize::try_from(instruction.params[index]).ok()?;
+
+      match mode {
+        Mode::Position if covered.contains(&target) => Some((*address, target)),
+        _ => None,
+      }
+    })
+    .collect()
+}
+
+fn unreached(size: usize, covered: &BTreeSet<usize>) -> Vec<Range<usize>> {
+  let mut ranges: Vec<Range<usize>> = vec![];
+
+  for address in (0..size).filter(|address| !covered.contains(address)) {
+    match ranges.last_mut() {
+      Some(range) if range.end == address => range.end += 1,
+      _ => ranges.push(address..address + 1),
+    }
+  }
+
+  ranges
+}
+
+impl Analysis {
+  // Render the control-flow graph in the Graphviz DOT format, one node per block holding its listing
+  // Indirect jumps point to a single `indirect` node
+  pub fn dot(&self) -> String {
+    let read =
+      |address: usize| self.image.get(address).copied().unwrap_or_default();
+    let mut dot = String::from("digraph intcode {\n");
+
+    dot.push_str("  node [shape=box, fontname=\"monospace\"];\n");
+
+    for block in &self.blocks {
+      let mut label = String::new();
+      let mut address = block.start;
+
+      while address < block.end {
+        let line = disassemble_at(address, self.image.len(), read);
+        let text = line.to_string().replace('\\', "\\\\").replace('"', "\\\"");
+
+        label.push_str(&text);
+        label.push_str("\\l");
+        address += line.size();
+      }
+
+      dot.push_str(&format!("  b{} [label=\"{}\"];\n", block.start, label));
+
+      for successor in &block.successors {
+        dot.push_str(&format!("  b{} -> b{};\n", block.start, successor));
+      }
+      if block.indirect {
+        dot.push_str(&format!(
+          "  b{} -> indirect [style=dashed];\n",
+          block.start
+        ));
+      }
+    }
+
+    if !self.indirect.is_empty() {
+      dot.push_str("  indirect [shape=ellipse];\n");
+    }
+
+    dot.push_str("}\n");
+    dot
+  }
+}
+
+// Summary of the analysis, listing everything that could need a closer look
+impl fmt::Display for Analysis {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    writeln!(
+      formatter,
+      "{} blocks, {} indirect jumps, {} writes into code",
+      self.blocks.len(),
+      self.indirect.len(),
+      self.writes.len()
+    )?;
+
+    for address in &self.indirect {
+      writeln!(formatter, "{:04}:  indirect jump", address)?;
+    }
+    for (address, target) in &self.writes {
+      writeln!(
+        formatter,
+        "{:04}:  writes into code at {:04}",
+        address, target
+      )?;
+    }
+    for range in &self.unreached {
+      writeln!(
+        formatter,
+        "{:04}:  {} unreached words, up to {:04}",
+        range.start,
+        range.len(),
+        range.end - 1
+      )?;
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{analyze, Block};
+  use crate::util::intcode::assemble;
+
+  #[test]
+  fn control_flow() {
+    let source = "
+      start:  Input [value]
+              Add #back, #0, [slot]       ; Return address
+              JumpIf #1, #double
+      back:   Output [value]
+              Halt
+      dead:   Output #0
+              Halt
+      double: Mul [value], #2, [value]
+              Add [value], #0, [start+1]  ; Writes into the first instruction
+              JumpIf #1, [slot]
+      value:  .data 0
+      slot:   .data 0
+    ";
+
+    let analysis = analyze(&assemble(source).unwrap());
+
+    let block = |start, end, successors: Vec<usize>, indirect| Block {
+      start,
+      end,
+      successors,
+      indirect,
+    };
+
+    assert_eq!(
+      analysis.blocks,
+      vec![
+        block(0, 9, vec![15], false),
+        block(9, 12, vec![], false),
+        block(15, 26, vec![], true),
+      ]
+    );
+    assert_eq!(analysis.indirect, vec![23]);
+    assert_eq!(analysis.writes, vec![(19, 1)]);
+    assert_eq!(analysis.unreached, vec![12..15, 26..28]);
+
+    let dot = analysis.dot();
+    assert!(dot.starts_with("digraph intcode {\n"));
+    assert!(dot.contains("  b0 -> b15;\n"));
+    assert!(dot.contains("  b15 -> indirect [style=dashed];\n"));
+    assert!(dot.contains("[label=\"0009:  Output [26]"));
+
+    assert_eq!(
+      analysis.to_string(),
+      "3 blocks, 1 indirect jumps, 1 writes into code
+0023:  indirect jump
+0019:  writes into code at 0001
+0012:  3 unreached words, up to 0014
+0026:  2 unreached words, up to 0027
+"
+    );
+
+    // 1104 is an Output with a superfluous mode, which the listing shows as data
+    // The JumpUnless opcode following it would read its parameters past the end of the image, it is shown as data too
+    let dot = analyze(&[1104, 5, 99]).dot();
+    assert!(dot.contains("0000:  DATA 1104"));
+    assert!(dot.contains("0001:  DATA 5"));
+  }
+}
@@ -0,0 +1,88 @@
+use super::{IntcodeError, Memory, Paged, Program, Queue, Tracer};
+
+// Text interface to a program, inputs being sent as lines and outputs decoded as characters
+// Values outside of the ASCII range are not text, they are kept in `answers`
+pub struct Ascii<M = Paged, T = ()> {
+  pub program: Program<Queue, M, T>,
+  pub answers: Vec<i128>,
+}
+
+impl<M, T> Ascii<M, T>
+where
+  M: Memory,
+  T: Tracer,
+{
+  pub fn new(program: Program<Queue, M, T>) -> Ascii<M, T> {
+    Ascii { program, answers: vec![] }
+  }
+
+  // Queue a line of input, a newline being added after it
+  pub fn send_line(&mut self, line: &str) {
+    let inputs = &mut self.program.io.inputs;
+
+    inputs.extend(line.bytes().map(i128::from));
+    inputs.push_back(i128::from(b'\n'));
+  }
+
+  // Queue a line of input, and return the text printed in response
+  pub fn send(&mut self, line: &str) -> Result<String, IntcodeError> {
+    self.send_line(line);
+    self.run()
+  }
+
+  // Run the program until it needs more input or halts, and return the text it printed
+  pub fn run(&mut self) -> Result<String, IntcodeError> {
+    self.program.run_until_blocked()?;
+
+    let mut text = String::new();
+
+    for value in self.program.io.outputs.drain(..) {
+      match value {
+        0..=127 => text.push(value as u8 as char),
+        _ => self.answers.push(value),
+      }
+    }
+
+    Ok(text)
+  }
+
+  pub fn halted(&self) -> bool {
+    self.program.halted
+  }
+
+  // Whether the program is blocked until another line is sent
+  pub fn waiting(&self) -> bool {
+    !self.program.halted && self.program.io.inputs.is_empty()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Ascii;
+  use crate::util::intcode::{assemble, Program};
+
+  #[test]
+  fn conversation() {
+    let source = "
+              Output #72
+              Output #105
+              Output #10
+      loop:   Input [char]
+              Output [char]
+              IfEquals [char], #10, [done]
+              JumpUnless [done], #loop
+              Output #1000
+              Halt
+      char:   .data 0
+      done:   .data 0
+    ";
+    let program = Program::new(assemble(source).unwrap(), vec![]);
+    let mut ascii = Ascii::new(program);
+
+    assert_eq!(ascii.run(), Ok("Hi\n".to_string()));
+    assert!(ascii.waiting());
+    assert_eq!(ascii.send("Hello"), Ok("Hello\n".to_string()));
+    assert_eq!(ascii.answers, vec![1000]);
+    assert!(ascii.halted());
+  }
+}
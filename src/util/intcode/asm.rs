@@ -0,0 +1,263 @@
+use std::{collections::HashMap, error::Error, fmt};
+
+use super::{mode::Mode, opcode::Opcode};
+
+// Error found in an assembly source, `line` starting at 1
+#[derive(Debug, PartialEq, Clone)]
+pub struct AssemblyError {
+  pub line: usize,
+  pub message: String,
+}
+
+impl fmt::Display for AssemblyError {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    write!(formatter, "line {}: {}", self.line, self.message)
+  }
+}
+
+impl Error for AssemblyError {}
+
+fn error(line: usize, message: String) -> AssemblyError {
+  AssemblyError { line, message }
+}
+
+// A statement, with its operands still to be resolved
+enum Item<'a> {
+  Instruction(Opcode, Vec<&'a str>),
+  Data(Vec<&'a str>),
+}
+
+struct Statement<'a> {
+  line: usize,
+  item: Item<'a>,
+}
+
+// Build an image from an assembly source
+// Every line holds an optional label, and an instruction or a directive, comments starting with `;`:
+//
+//   loop:   Input [value]            ; Mnemonics are the names of the opcodes
+//           JumpUnless [value], #end ; Operands are `[address]`, `#value` or `rb+offset`
+//           Output rb-1              ; Values can be numbers, labels or `label+offset`
+//           JumpIf #1, #loop
+//   end:    Halt
+//   value:  .data 0                  ; `.data` (or `DATA`) emits raw words
+//
+// Labels only made of digits (as found in disassembled listings) check the address they are found at
+pub fn assemble(source: &str) -> Result<Vec<i128>, AssemblyError> {
+  let mut labels: HashMap<&str, usize> = HashMap::new();
+  let mut statements = vec![];
+  let mut address = 0;
+
+  // First pass, lay out every statement to find out where labels are
+  for (index, line) in source.lines().enumerate() {
+    let number = index + 1;
+    let mut text = line.split(';').next().unwrap_or_default().trim();
+
+    while let Some(colon) = text.find(':') {
+      let label = &text[..colon];
+
+      if label.chars().all(|c| c.is_ascii_digit()) && !label.is_empty() {
+        if label.parse::<usize>() != Ok(address) {
+          let message =
+            format!("expected address {}, found {}", label, address);
+          return Err(error(number, message));
+        }
+      } else if is_label(label) {
+        if labels.insert(label, address).is_some() {
+          return Err(error(number, format!("duplicate label `{}`", label)));
+        }
+      } else {
+        break;
+      }
+
+      text = text[colon + 1..].trim_start();
+    }
+
+    if text.is_empty() {
+      continue;
+    }
+
+    let (name, operands) = match text.find(char::is_whitespace) {
+      Some(space) => (&text[..space], text[space..].trim()),
+      None => (text, ""),
+    };
+    let operands: Vec<&str> = if operands.is_empty() {
+      vec![]
+    } else {
+      operands.split(',').map(str::trim).collect()
+    };
+
+    let item = match name {
+      ".data" | "DATA" => Item::Data(operands),
+      _ => instruction(number, name, operands)?,
+    };
+
+    let size = match item {
+      Item::Instruction(op, _) => op.arity() + 1,
+      Item::Data(ref values) => values.len(),
+    };
+
+    statements.push(Statement { line: number, item });
+    address += size;
+  }
+
+  // Second pass, encode every statement now that labels are known
+  let mut image = Vec::with_capacity(address);
+
+  for statement in statements {
+    let line = statement.line;
+    let resolve = |token| value(line, token, &labels);
+
+    match statement.item {
+      Item::Instruction(op, operands) => {
+        image.push(op.encode());
+        for operand in operands {
+          image.push(resolve(operand)?);
+        }
+      }
+      Item::Data(values) => {
+        for token in values {
+          image.push(resolve(token)?);
+        }
+      }
+    }
+  }
+
+  Ok(image)
+}
+
+// Parse an instruction, its operands are stripped from their mode syntax
+fn instruction<'a>(
+  line: usize, name: &str, operands: Vec<&'a str>,
+) -> Result<Item<'a>, AssemblyError> {
+  let mut modes = [Mode::Position; 3];
+  let mut values = vec![];
+
+  if operands.len() > modes.len() {
+    return Err(error(line, format!("too many operands for `{}`", name)));
+  }
+
+  for (index, operand) in operands.iter().enumerate() {
+    let (mode, value) = if operand.starts_with('[') && operand.ends_with(']') {
+      (Mode::Position, &operand[1..operand.len() - 1])
+    } else if let Some(value) = operand.strip_prefix('#') {
+      (Mode::Immediate, value)
+    } else if let Some(offset) = operand.strip_prefix("rb") {
+      (Mode::Relative, offset.strip_prefix('+').unwrap_or(offset))
+    } else {
+      return Err(error(line, format!("invalid operand `{}`", operand)));
+    };
+
+    modes[index] = mode;
+    values.push(value.trim());
+  }
+
+  let op = match Opcode::from_name(name, modes) {
+    Some(op) => op,
+    None => return Err(error(line, format!("unknown mnemonic `{}`", name))),
+  };
+
+  if op.arity() != values.len() {
+    let message = format!(
+      "`{}` expects {} operands, found {}",
+      name,
+      op.arity(),
+      values.len()
+    );
+    return Err(error(line, message));
+  }
+
+  Ok(Item::Instruction(op, values))
+}
+
+fn is_label(token: &str) -> bool {
+  let mut chars = token.chars();
+
+  match chars.next() {
+    Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+      chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+    _ => false,
+  }
+}
+
+// Resolve a number, a label or a label with an offset (`label+1`, `label-1`)
+fn value(
+  line: usize, token: &str, labels: &HashMap<&str, usize>,
+) -> Result<i128, AssemblyError> {
+  if let Ok(value) = token.parse::<i128>() {
+    return Ok(value);
+  }
+
+  let (label, offset) = match token.find(&['+', '-'][..]) {
+    Some(sign) => match token[sign..].parse::<i128>() {
+      Ok(offset) => (token[..sign].trim_end(), offset),
+      Err(_) => return Err(error(line, format!("invalid value `{}`", token))),
+    },
+    None => (token, 0),
+  };
+
+  match labels.get(label) {
+    Some(address) => Ok(*address as i128 + offset),
+    None if is_label(label) => {
+      Err(error(line, format!("undefined label `{}`", label)))
+    }
+    None => Err(error(line, format!("invalid value `{}`", token))),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{assemble, AssemblyError};
+  use crate::util::{
+    self,
+    intcode::{disassemble, load, Program},
+  };
+
+  #[test]
+  fn assembly() {
+    let source = "
+      ; Outputs 1 if the input equals 8, 0 otherwise
+      start:  Input [value]
+              IfEquals [value], #8, [value]
+              Output [value]
+              Halt
+      value:  .data -1, value+1
+    ";
+
+    let image = assemble(source).unwrap();
+    assert_eq!(image, vec![3, 9, 1008, 9, 8, 9, 4, 9, 99, -1, 10]);
+
+    let mut program = Program::new(image, vec![8]);
+    assert_eq!(program.execute_for_output(), Ok(Some(1)));
+  }
+
+  #[test]
+  fn errors() {
+    let data = vec![
+      ("Add #1, #2", 1, "`Add` expects 3 operands, found 2"),
+      ("\n  Jump #1, #2", 2, "unknown mnemonic `Jump`"),
+      ("Output [nowhere]", 1, "undefined label `nowhere`"),
+      ("Output 12", 1, "invalid operand `12`"),
+      ("a: Halt\na: Halt", 2, "duplicate label `a`"),
+      ("0000: Halt\n0002: Halt", 2, "expected address 0002, found 1"),
+    ];
+
+    for (source, line, message) in data {
+      let message = message.to_string();
+      assert_eq!(assemble(source), Err(AssemblyError { line, message }));
+    }
+  }
+
+  #[test]
+  fn round_trip() {
+    for day in &[2, 5, 7, 9, 11, 13, 15] {
+      let image = load(util::get_input(2019, *day)).unwrap();
+
+      let listing: Vec<String> =
+        disassemble(&image).iter().map(|line| line.to_string()).collect();
+
+      assert_eq!(assemble(&listing.join("\n")), Ok(image));
+    }
+  }
+}
@@ -0,0 +1,145 @@
+extern crate test;
+
+use itertools::Itertools;
+use test::Bencher;
+
+use super::{legacy, Circuit, Engine, Program, RunState};
+use crate::util;
+
+// Every workload is also timed on the per-word state machine the decoder replaced (`*_per_tick`),
+// so the speedup can be measured with `cargo bench`
+
+fn image(day: u8) -> Vec<i128> {
+  super::load(util::get_input(2019, day)).expect("invalid input")
+}
+
+// Every noun/verb pair from day 2
+#[bench]
+fn gravity_assist(bencher: &mut Bencher) {
+  let stack = image(2);
+
+  bencher.iter(|| {
+    for noun in 0..=99 {
+      for verb in 0..=99 {
+        let mut program = Program::new(stack.clone(), vec![]);
+        program.stack[1] = noun;
+        program.stack[2] = verb;
+        program.execute().unwrap();
+      }
+    }
+  });
+}
+
+#[bench]
+fn gravity_assist_per_tick(bencher: &mut Bencher) {
+  let stack = image(2);
+
+  bencher.iter(|| {
+    for noun in 0..=99 {
+      for verb in 0..=99 {
+        let mut program = legacy::Program::new(stack.clone(), vec![]);
+        program.stack[1] = noun;
+        program.stack[2] = verb;
+        program.run_until_event();
+      }
+    }
+  });
+}
+
+// Every phase permutation of the day 7 feedback loop
+#[bench]
+fn amplification_circuit(bencher: &mut Bencher) {
+  let stack = image(7);
+
+  bencher.iter(|| {
+    for settings in (5..10).permutations(5) {
+      let circuit = Circuit::with_copies(
+        5,
+        stack.clone(),
+        |index| vec![settings[index]],
+        true,
+      );
+      circuit.execute(0).unwrap();
+    }
+  });
+}
+
+#[bench]
+fn amplification_circuit_per_tick(bencher: &mut Bencher) {
+  let stack = image(7);
+
+  bencher.iter(|| {
+    for settings in (5..10).permutations(5) {
+      feedback_loop(&stack, &settings);
+    }
+  });
+}
+
+// Signal sent to the thrusters by amplifiers running on the per-word state machine
+fn feedback_loop(stack: &[i128], settings: &[i128]) -> i128 {
+  let mut amplifiers: Vec<legacy::Program> = settings
+    .iter()
+    .map(|setting| legacy::Program::new(stack.to_vec(), vec![*setting]))
+    .collect();
+  let mut signal = 0;
+
+  loop {
+    for amplifier in amplifiers.iter_mut() {
+      amplifier.io.inputs.push_back(signal);
+
+      match amplifier.run_until_event() {
+        RunState::Output(value) => signal = value,
+        _ => return signal,
+      }
+    }
+  }
+}
+
+// Day 9 BOOST program in sensor boost mode
+#[bench]
+fn sensor_boost(bencher: &mut Bencher) {
+  let stack = image(9);
+
+  bencher.iter(|| {
+    let mut program = Program::new(stack.clone(), vec![2]);
+    program.execute().unwrap();
+  });
+}
+
+#[bench]
+fn sensor_boost_per_tick(bencher: &mut Bencher) {
+  let stack = image(9);
+
+  bencher.iter(|| {
+    let mut program = legacy::Program::new(stack.clone(), vec![2]);
+    program.run_until_event();
+  });
+}
+
+// Same program, through the block compiler
+#[bench]
+fn sensor_boost_compiled(bencher: &mut Bencher) {
+  let stack = image(9);
+
+  bencher.iter(|| {
+    let mut program = Program::new(stack.clone(), vec![2]);
+    program.engine = Engine::Compiler;
+    program.execute().unwrap();
+  });
+}
+
+// The baseline computes the same results as the current decoder
+#[test]
+fn per_tick() {
+  let stack = image(9);
+
+  let mut baseline = legacy::Program::new(stack.clone(), vec![1]);
+  let mut program = Program::new(stack, vec![1]);
+  assert_eq!(Ok(baseline.run_until_event()), program.run_until_event());
+
+  let stack = image(7);
+  let settings = vec![9, 8, 7, 6, 5];
+  let circuit =
+    Circuit::with_copies(5, stack.clone(), |index| vec![settings[index]], true);
+  assert_eq!(Ok(feedback_loop(&stack, &settings)), circuit.execute(0));
+}
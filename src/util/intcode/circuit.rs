@@ -1,42 +1,431 @@
-use super::Program;
+use std::{
+  error::Error,
+  fmt, io,
+  sync::{
+    mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    Arc, Mutex,
+  },
+  thread,
+  time::Duration,
+};
 
-pub struct Circuit<F> {
-  programs: Vec<Program>,
-  inputs: F,
+use super::{
+  Engine, ExitStatus, IntcodeError, IntcodeIo, Limits, Program, Reason,
+  RunState,
+};
+
+// How long a blocked program waits for an input before looking for a deadlock
+const POLL: Duration = Duration::from_millis(5);
+
+// Reason why a circuit could not produce a signal
+//  * CircuitError::Program means a program of the circuit failed
+//  * CircuitError::NoOutput means a program halted before emitting any signal
+//  * CircuitError::Deadlock lists the programs that were all waiting for an input none of them would send
+//  * CircuitError::Empty means the circuit has no program to send the signal to
+#[derive(Debug, PartialEq, Clone)]
+pub enum CircuitError {
+  Program(usize, IntcodeError),
+  NoOutput(usize),
+  Deadlock(Vec<usize>),
+  Empty,
+}
+
+impl fmt::Display for CircuitError {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match self {
+      CircuitError::Program(node, err) => {
+        write!(formatter, "program {}: {}", node, err)
+      }
+      CircuitError::NoOutput(node) => {
+        write!(formatter, "program {} halted without any output", node)
+      }
+      CircuitError::Deadlock(nodes) => {
+        write!(formatter, "programs {:?} are all waiting for an input", nodes)
+      }
+      CircuitError::Empty => {
+        write!(formatter, "a circuit needs at least one program")
+      }
+    }
+  }
+}
+
+impl Error for CircuitError {}
+
+// Programs wired in a line, each one receiving the outputs of the previous one
+// With a feedback loop, the outputs of the last program are also sent back to the first one
+pub struct Circuit {
+  stack: Vec<i128>,
+  settings: Vec<Vec<i128>>,
   feedback: bool,
+  limits: Limits,
+  engine: Engine,
 }
 
-impl<F> Circuit<F>
-where
-  F: Fn(usize, usize, i128) -> Vec<i128>,
-{
-  pub fn with_copies(
-    count: usize, stack: Vec<i128>, inputs: F, feedback: bool,
-  ) -> Self {
-    let programs =
-      (0..count).map(|_| Program::new(stack.clone(), vec![])).collect();
+impl Circuit {
+  // Wire `count` copies of a program, each one receiving `settings(index)` before any signal
+  pub fn with_copies<F>(
+    count: usize, stack: Vec<i128>, settings: F, feedback: bool,
+  ) -> Self
+  where
+    F: Fn(usize) -> Vec<i128>,
+  {
+    let settings = (0..count).map(settings).collect();
 
-    Self { programs, inputs, feedback }
+    Self {
+      stack,
+      settings,
+      feedback,
+      limits: Limits::default(),
+      engine: Engine::default(),
+    }
+  }
+
+  // Apply the same limits to every program of the circuit
+  pub fn with_limits(mut self, limits: Limits) -> Self {
+    self.limits = limits;
+    self
   }
 
-  pub fn execute(&mut self) -> i128 {
-    let mut cycle = 0;
-    let mut output = 0;
+  // Run every program of the circuit with the given engine
+  pub fn with_engine(mut self, engine: Engine) -> Self {
+    self.engine = engine;
+    self
+  }
+
+  // Send `signal` to the first program, and return the last signal emitted by the last one
+  // Programs run one after the other on the current thread, until one of them halts
+  pub fn execute(&self, mut signal: i128) -> Result<i128, CircuitError> {
+    let last = match self.settings.len().checked_sub(1) {
+      Some(last) => last,
+      None => return Err(CircuitError::Empty),
+    };
+    let mut result = None;
+    let mut programs: Vec<Program> = self
+      .settings
+      .iter()
+      .map(|settings| {
+        let mut program = Program::new(self.stack.clone(), settings.clone());
+        program.limits = self.limits;
+        program.engine = self.engine;
+        program
+      })
+      .collect();
+
     loop {
-      for (index, program) in &mut self.programs.iter_mut().enumerate() {
-        program.inputs = (self.inputs)(cycle, index, output);
+      for (index, program) in programs.iter_mut().enumerate() {
+        program.io.inputs.push_back(signal);
 
-        match program.execute_for_output() {
-          Some(retval) => output = retval,
-          None => panic!("program had no output"),
+        match program.execute() {
+          Ok(ExitStatus::Output(value)) => signal = value,
+          // Without a feedback loop, every program has to emit a signal
+          Ok(ExitStatus::Halted) => {
+            return result.ok_or(CircuitError::NoOutput(index))
+          }
+          Err(err) => return Err(CircuitError::Program(index, err)),
         }
+
+        if index == last {
+          result = Some(signal);
+        }
+      }
+
+      if !self.feedback {
+        return Ok(signal);
       }
+    }
+  }
+
+  // Same as `execute`, with every program running on its own thread, connected by channels
+  // Once a program halts, the others are stopped as their neighbours go away
+  pub fn execute_threaded(&self, signal: i128) -> Result<i128, CircuitError> {
+    let count = self.settings.len();
+    if count == 0 {
+      return Err(CircuitError::Empty);
+    }
+
+    let activity = Arc::new(Mutex::new(Activity::new(count)));
+    let (senders, receivers): (Vec<Sender<i128>>, Vec<_>) =
+      (0..=count).map(|_| mpsc::channel()).unzip();
+
+    // Every program starts with its settings, the first one also receiving the signal
+    for (node, settings) in self.settings.iter().enumerate() {
+      let signal = if node == 0 { Some(signal) } else { None };
+
+      for value in settings.iter().copied().chain(signal) {
+        activity.lock().unwrap().in_flight += 1;
+        let _ = senders[node].send(value);
+      }
+    }
+
+    // Only a feedback loop sends anything more to the first program
+    let first = self.feedback.then(|| senders[0].clone());
+    let mut senders = senders.into_iter().skip(1);
+    let mut receivers = receivers.into_iter();
+    let mut handles = vec![];
+
+    for node in 0..count {
+      let receiver = receivers.next().unwrap();
+      let sender = senders.next().unwrap();
+      let link = Link { node, receiver, sender, activity: activity.clone() };
+      let mut program = Program::with_io(self.stack.clone(), link);
+      program.limits = self.limits;
+      program.engine = self.engine;
+
+      handles.push(thread::spawn(move || {
+        let state = program.run_until_blocked();
+        program.io.stop(state == Ok(RunState::Halted));
+        state
+      }));
+    }
+
+    let (output, mut result) = (receivers.next().unwrap(), None);
+
+    for value in output {
+      result = Some(value);
+
+      if let Some(first) = &first {
+        // The value is still in flight until it is received by the first program
+        let _ = first.send(value);
+      } else {
+        activity.lock().unwrap().in_flight -= 1;
+      }
+    }
+
+    drop(first);
+
+    let states: Vec<_> = handles
+      .into_iter()
+      .map(|handle| handle.join().expect("a program thread panicked"))
+      .collect();
+
+    // Programs stopped by one of their neighbours going away, or by a deadlock, did not fail
+    let stopped = [io::ErrorKind::BrokenPipe, io::ErrorKind::WouldBlock];
+
+    for (node, state) in states.into_iter().enumerate() {
+      if let Err(err) = state {
+        if !stopped.iter().any(|kind| err.reason == Reason::Io(*kind)) {
+          return Err(CircuitError::Program(node, err));
+        }
+      }
+    }
+
+    let mut activity = activity.lock().unwrap();
+
+    if let Some(nodes) = activity.deadlock.take() {
+      return Err(CircuitError::Deadlock(nodes));
+    }
 
-      if !self.feedback || self.programs[self.programs.len() - 1].halted {
-        return output;
+    // Blame the first program that halted without any output, as `execute` does
+    let silent = activity.silent.iter().position(|silent| *silent);
+
+    result.ok_or(CircuitError::NoOutput(silent.unwrap_or(count - 1)))
+  }
+}
+
+// What every program of a threaded circuit is doing
+struct Activity {
+  blocked: Vec<bool>,
+  stopped: Vec<bool>,
+  // Whether any program reached its Halt instruction, the others are then shutting down
+  halted: bool,
+  // Programs that emitted a value, and those that halted before emitting any
+  emitted: Vec<bool>,
+  silent: Vec<bool>,
+  // Number of values sent but not received yet
+  in_flight: usize,
+  deadlock: Option<Vec<usize>>,
+}
+
+impl Activity {
+  fn new(count: usize) -> Activity {
+    Activity {
+      blocked: vec![false; count],
+      stopped: vec![false; count],
+      halted: false,
+      emitted: vec![false; count],
+      silent: vec![false; count],
+      in_flight: 0,
+      deadlock: None,
+    }
+  }
+
+  // Whether no running program will ever receive anything
+  fn deadlocked(&self) -> bool {
+    !self.halted
+      && self.in_flight == 0
+      && self.blocked.iter().zip(&self.stopped).all(|(b, s)| *b || *s)
+  }
+}
+
+// Channels connecting a program to its neighbours in a threaded circuit
+struct Link {
+  node: usize,
+  receiver: Receiver<i128>,
+  sender: Sender<i128>,
+  activity: Arc<Mutex<Activity>>,
+}
+
+impl Link {
+  fn stop(&self, halted: bool) {
+    let mut activity = self.activity.lock().unwrap();
+
+    activity.stopped[self.node] = true;
+    activity.halted |= halted;
+    activity.silent[self.node] = halted && !activity.emitted[self.node];
+  }
+}
+
+impl IntcodeIo for Link {
+  fn read(&mut self) -> io::Result<Option<i128>> {
+    loop {
+      match self.receiver.recv_timeout(POLL) {
+        Ok(value) => {
+          let mut activity = self.activity.lock().unwrap();
+          activity.in_flight -= 1;
+          activity.blocked[self.node] = false;
+
+          return Ok(Some(value));
+        }
+        Err(RecvTimeoutError::Disconnected) => {
+          return Err(io::ErrorKind::BrokenPipe.into())
+        }
+        Err(RecvTimeoutError::Timeout) => {
+          let mut activity = self.activity.lock().unwrap();
+          activity.blocked[self.node] = true;
+
+          if activity.deadlock.is_none() && activity.deadlocked() {
+            let nodes = activity.blocked.iter().zip(&activity.stopped);
+            let nodes = nodes.enumerate().filter(|(_, (b, s))| **b && !**s);
+
+            activity.deadlock = Some(nodes.map(|(node, _)| node).collect());
+          }
+
+          if activity.deadlock.is_some() {
+            return Err(io::ErrorKind::WouldBlock.into());
+          }
+        }
       }
+    }
+  }
+
+  fn write(&mut self, value: i128) -> io::Result<()> {
+    let mut activity = self.activity.lock().unwrap();
+    activity.in_flight += 1;
+    activity.emitted[self.node] = true;
+    drop(activity);
+
+    if self.sender.send(value).is_err() {
+      self.activity.lock().unwrap().in_flight -= 1;
+      return Err(io::ErrorKind::BrokenPipe.into());
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Circuit, CircuitError};
+  use crate::util::intcode::{Engine, IntcodeError, Limit, Limits, Reason};
+
+  #[test]
+  fn threaded() {
+    let stack = vec![
+      3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27,
+      1001, 28, -1, 28, 1005, 28, 6, 99, 0, 0, 5,
+    ];
+    let settings = [9, 8, 7, 6, 5];
+    let circuit =
+      Circuit::with_copies(5, stack, |index| vec![settings[index]], true);
+
+    assert_eq!(circuit.execute(0), Ok(139629729));
+    assert_eq!(circuit.execute_threaded(0), Ok(139629729));
+  }
+
+  #[test]
+  fn errors() {
+    // Halts right away
+    let circuit = Circuit::with_copies(3, vec![99], |_| vec![], false);
+    assert_eq!(circuit.execute(0), Err(CircuitError::NoOutput(0)));
+    assert_eq!(circuit.execute_threaded(0), Err(CircuitError::NoOutput(0)));
+
+    // Forwards its input, unless its setting is 0
+    let stack = vec![3, 13, 1005, 13, 6, 99, 3, 14, 4, 14, 99, 0, 0, 0, 0];
+    let circuit = Circuit::with_copies(
+      3,
+      stack,
+      |index| vec![if index == 1 { 0 } else { 1 }],
+      false,
+    );
+    assert_eq!(circuit.execute(0), Err(CircuitError::NoOutput(1)));
+    assert_eq!(circuit.execute_threaded(0), Err(CircuitError::NoOutput(1)));
+
+    // Fails after reading its input, leaving the other program waiting
+    let circuit =
+      Circuit::with_copies(2, vec![3, 5, 98, 0, 0, 0], |_| vec![], true);
+    let error = CircuitError::Program(
+      0,
+      IntcodeError { pointer: 2, opcode: 98, reason: Reason::InvalidOpcode },
+    );
+    assert_eq!(circuit.execute(0), Err(error.clone()));
+    assert_eq!(circuit.execute_threaded(0), Err(error));
+
+    // Waits for two inputs before adding them
+    let stack = vec![3, 13, 3, 14, 1, 13, 14, 13, 4, 13, 1105, 1, 0];
+    let circuit = Circuit::with_copies(2, stack, |_| vec![], true);
+    assert_eq!(
+      circuit.execute_threaded(0),
+      Err(CircuitError::Deadlock(vec![0, 1]))
+    );
+  }
+
+  #[test]
+  fn empty() {
+    let circuit = Circuit::with_copies(0, vec![99], |_| vec![], false);
+
+    assert_eq!(circuit.execute(0), Err(CircuitError::Empty));
+    assert_eq!(circuit.execute_threaded(0), Err(CircuitError::Empty));
+  }
+
+  #[test]
+  fn limits() {
+    // Loops forever
+    let circuit = Circuit::with_copies(3, vec![1105, 1, 0], |_| vec![], false)
+      .with_limits(Limits::instructions(100));
+
+    let error = CircuitError::Program(
+      0,
+      IntcodeError {
+        pointer: 0,
+        opcode: 1105,
+        reason: Reason::LimitExceeded(Limit::Instructions(100)),
+      },
+    );
+
+    assert_eq!(circuit.execute(0), Err(error.clone()));
+    assert_eq!(circuit.execute_threaded(0), Err(error));
+  }
+  #[test]
+  fn engines() {
+    let stack = vec![
+      3, 52, 1001, 52, -5, 52, 3, 53, 1, 52, 56, 54, 1007, 54, 5, 55, 1005, 55,
+      26, 1001, 54, -5, 54, 1105, 1, 12, 1, 53, 54, 53, 1008, 54, 0, 55, 1001,
+      55, 1, 55, 2, 53, 55, 53, 4, 53, 1001, 56, -1, 56, 1005, 56, 6, 99, 0, 0,
+      0, 0, 10,
+    ];
+    let settings = [9, 7, 8, 5, 6];
+
+    for engine in &[Engine::Interpreter, Engine::Compiler] {
+      let circuit = Circuit::with_copies(
+        5,
+        stack.clone(),
+        |index| vec![settings[index]],
+        true,
+      )
+      .with_engine(*engine);
 
-      cycle += 1;
+      assert_eq!(circuit.execute(0), Ok(18216), "{:?}", engine);
+      assert_eq!(circuit.execute_threaded(0), Ok(18216), "{:?}", engine);
     }
   }
 }
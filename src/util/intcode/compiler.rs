@@ -0,0 +1,325 @@
+use std::{env, sync::Arc};
+
+use super::{
+  instruction::Instruction, mode::Mode, opcode::Opcode, Extensions, Word,
+};
+
+// How a program executes its instructions
+//  * Engine::Interpreter decodes and executes instructions one at a time
+//  * Engine::Compiler translates runs of arithmetic, comparison, SetBase and jump instructions into blocks, cached by
+//    address and dropped as soon as the program writes into them. Everything else (inputs, outputs, Halt, extensions,
+//    and any instruction that would fail) is left to the interpreter.
+//
+// Compiled blocks only cover the stack, and do not see words changed directly through `Program::stack` once the
+// program started: use `Program::poke` instead. Programs with a tracer are always interpreted.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Engine {
+  Interpreter,
+  Compiler,
+}
+
+impl Default for Engine {
+  // Every test can be run against the compiler with `INTCODE_ENGINE=compiler cargo test`
+  fn default() -> Engine {
+    if cfg!(test) && env::var("INTCODE_ENGINE").as_deref() == Ok("compiler") {
+      Engine::Compiler
+    } else {
+      Engine::Interpreter
+    }
+  }
+}
+
+// Operand read by a compiled instruction, position addresses being resolved at compilation
+#[derive(Debug, Clone)]
+pub(super) enum Operand<W> {
+  Value(W),
+  Position(usize),
+  Relative(W),
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum Target<W> {
+  Position(usize),
+  Relative(W),
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum Op<W> {
+  Add(Operand<W>, Operand<W>, Target<W>),
+  Mul(Operand<W>, Operand<W>, Target<W>),
+  Less(Operand<W>, Operand<W>, Target<W>),
+  Equals(Operand<W>, Operand<W>, Target<W>),
+  SetBase(Operand<W>),
+  JumpIf(Operand<W>, Operand<W>),
+  JumpUnless(Operand<W>, Operand<W>),
+}
+
+// A compiled instruction, with the address of the instruction following it
+#[derive(Debug, Clone)]
+pub(super) struct Step<W> {
+  pub next: usize,
+  pub op: Op<W>,
+}
+
+// Straight-line run of compiled instructions covering `start..end`, only the last one can jump
+// Blocks without any step start with an instruction left to the interpreter
+#[derive(Debug, Clone)]
+pub(super) struct Block<W> {
+  pub start: usize,
+  pub end: usize,
+  pub steps: Vec<Step<W>>,
+}
+
+// Compile the instructions starting at `start`, up to the first one that cannot be compiled, or the first jump
+pub(super) fn compile<W: Word>(
+  start: usize, stack: &[W], extensions: &Extensions<W>,
+) -> Block<W> {
+  let read = |address: usize| match stack.get(address) {
+    Some(word) => word.clone(),
+    None => W::zero(),
+  };
+
+  let mut steps = vec![];
+  let mut address = start;
+
+  while let Ok(instruction) = Instruction::decode(address, read, extensions) {
+    let next = match instruction.next(address) {
+      Some(next) if next <= stack.len() => next,
+      _ => break,
+    };
+
+    let op = match translate(instruction) {
+      Some(op) => op,
+      None => break,
+    };
+    let jumps = matches!(op, Op::JumpIf(..) | Op::JumpUnless(..));
+
+    steps.push(Step { next, op });
+    address = next;
+
+    if jumps {
+      break;
+    }
+  }
+
+  Block { start, end: address, steps }
+}
+
+fn translate<W: Word>(instruction: Instruction<W>) -> Option<Op<W>> {
+  let [p1, p2, p3] = instruction.params;
+
+  let op = match instruction.op {
+    Opcode::Add(m1, m2, m3) => {
+      Op::Add(operand(m1, p1)?, operand(m2, p2)?, target(m3, p3)?)
+    }
+    Opcode::Mul(m1, m2, m3) => {
+      Op::Mul(operand(m1, p1)?, operand(m2, p2)?, target(m3, p3)?)
+    }
+    Opcode::IfLess(m1, m2, m3) => {
+      Op::Less(operand(m1, p1)?, operand(m2, p2)?, target(m3, p3)?)
+    }
+    Opcode::IfEquals(m1, m2, m3) => {
+      Op::Equals(operand(m1, p1)?, operand(m2, p2)?, target(m3, p3)?)
+    }
+    Opcode::SetBase(m1, ..) => Op::SetBase(operand(m1, p1)?),
+    Opcode::JumpIf(m1, m2, _) => Op::JumpIf(operand(m1, p1)?, operand(m2, p2)?),
+    Opcode::JumpUnless(m1, m2, _) => {
+      Op::JumpUnless(operand(m1, p1)?, operand(m2, p2)?)
+    }
+    _ => return None,
+  };
+
+  Some(op)
+}
+
+// Operands that would make the instruction fail are not compiled
+fn operand<W: Word>(mode: Mode, value: W) -> Option<Operand<W>> {
+  match mode {
+    Mode::Immediate => Some(Operand::Value(value)),
+    Mode::Position => address(&value).map(Operand::Position),
+    Mode::Relative => Some(Operand::Relative(value)),
+  }
+}
+
+fn target<W: Word>(mode: Mode, value: W) -> Option<Target<W>> {
+  match mode {
+    Mode::Immediate => None,
+    Mode::Position => address(&value).map(Target::Position),
+    Mode::Relative => Some(Target::Relative(value)),
+  }
+}
+
+pub(super) fn address<W: Word>(value: &W) -> Option<usize> {
+  if *value < W::zero() {
+    return None;
+  }
+
+  value.to_usize()
+}
+
+// Number of times an address must be reached before a block is compiled from it
+// Code only run once (or rewritten as it runs, such as the day 2 program) is left to the interpreter
+const THRESHOLD: u32 = 16;
+
+// Compiled blocks by start address, with the number of blocks covering every word of the stack
+#[derive(Debug, Clone)]
+pub(super) struct Cache<W> {
+  blocks: Vec<Option<Arc<Block<W>>>>,
+  hits: Vec<u32>,
+  coverage: Vec<u32>,
+  // Size of the largest block ever compiled, bounding where blocks covering an address can start
+  longest: usize,
+}
+
+impl<W> Cache<W> {
+  pub fn new() -> Cache<W> {
+    Cache { blocks: vec![], hits: vec![], coverage: vec![], longest: 0 }
+  }
+
+  pub fn get(&self, address: usize) -> Option<Arc<Block<W>>> {
+    self.blocks.get(address).cloned().flatten()
+  }
+
+  // Count a visit to `address`, telling whether a block should be compiled from there
+  pub fn hot(&mut self, address: usize, size: usize) -> bool {
+    if address >= size {
+      return false;
+    }
+    if self.hits.len() < size {
+      self.hits.resize(size, 0);
+    }
+
+    self.hits[address] += 1;
+    self.hits[address] >= THRESHOLD
+  }
+
+  pub fn insert(&mut self, block: Block<W>) -> Arc<Block<W>> {
+    if self.coverage.len() < block.end {
+      self.coverage.resize(block.end, 0);
+    }
+    if self.blocks.len() <= block.start {
+      self.blocks.resize(block.start + 1, None);
+    }
+    for count in &mut self.coverage[block.start..block.end] {
+      *count += 1;
+    }
+    self.longest = self.longest.max(block.end - block.start);
+
+    let start = block.start;
+    let block = Arc::new(block);
+    self.blocks[start] = Some(block.clone());
+    block
+  }
+
+  // Drop every block covering `address`, telling whether there was any
+  // Those blocks have to get hot again before being compiled, so code rewriting itself is mostly interpreted
+  pub fn invalidate(&mut self, address: usize) -> bool {
+    if self.coverage.get(address).copied().unwrap_or_default() == 0 {
+      return false;
+    }
+
+    let first = (address + 1).saturating_sub(self.longest);
+    let last = address.min(self.blocks.len().saturating_sub(1));
+
+    for start in first..=last {
+      let covers = match &self.blocks[start] {
+        Some(block) => address < block.end,
+        None => false,
+      };
+
+      if covers {
+        if let Some(block) = self.blocks[start].take() {
+          for count in &mut self.coverage[block.start..block.end] {
+            *count -= 1;
+          }
+        }
+        self.hits[start] = 0;
+      }
+    }
+
+    true
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Engine;
+  use crate::util::{
+    self,
+    intcode::{self, assemble, Limit, Limits, Program, Reason, RunState},
+  };
+
+  fn run(stack: Vec<i128>, inputs: Vec<i128>, engine: Engine) -> Program {
+    let mut program = Program::new(stack, inputs);
+    program.engine = engine;
+    program.run_until_blocked().expect("invalid program");
+    program
+  }
+
+  #[test]
+  fn days() {
+    let data = vec![(5, vec![5]), (9, vec![1]), (9, vec![2])];
+
+    for (day, inputs) in data {
+      let stack = intcode::load(util::get_input(2019, day)).unwrap();
+
+      let interpreted = run(stack.clone(), inputs.clone(), Engine::Interpreter);
+      let compiled = run(stack, inputs, Engine::Compiler);
+
+      assert_eq!(compiled.io.outputs, interpreted.io.outputs);
+      assert_eq!(compiled.executed(), interpreted.executed());
+      assert_eq!(compiled.snapshot(), interpreted.snapshot());
+    }
+  }
+
+  #[test]
+  fn self_modifying() {
+    // Sums 99 down to 0 by rewriting an immediate operand of the loop on every iteration
+    let source = "
+      loop:   Add [count], #-1, [count]
+              Add [count], #0, [step+1]
+      step:   Add #0, [total], [total]
+              JumpIf [count], #loop
+              Output [total]
+              Halt
+      count:  .data 100
+      total:  .data 0
+    ";
+
+    let stack = assemble(source).unwrap();
+    let interpreted = run(stack.clone(), vec![], Engine::Interpreter);
+    let compiled = run(stack, vec![], Engine::Compiler);
+
+    assert_eq!(interpreted.io.outputs, vec![4950]);
+    assert_eq!(compiled.io.outputs, interpreted.io.outputs);
+    assert_eq!(compiled.snapshot(), interpreted.snapshot());
+  }
+
+  #[test]
+  fn faults() {
+    // Doubles 7 until it overflows, the error is reported by the interpreter
+    let stack = vec![1002, 9, 2, 9, 1105, 1, 0, 99, 0, 7];
+    let mut program = Program::new(stack, vec![]);
+    program.engine = Engine::Compiler;
+
+    let error = program.run_until_blocked().unwrap_err();
+    assert_eq!((error.pointer, error.reason), (0, Reason::Overflow));
+    assert_eq!(program.executed(), 248);
+
+    // Blocks do not go beyond instruction limits
+    let mut program = Program::new(vec![1101, 1, 2, 7, 1105, 1, 0, 0], vec![]);
+    program.engine = Engine::Compiler;
+    program.limits = Limits::instructions(101);
+
+    let limit = Reason::LimitExceeded(Limit::Instructions(101));
+    assert_eq!(
+      program.run_until_blocked().map_err(|err| err.reason),
+      Err(limit)
+    );
+    assert_eq!(program.executed(), 101);
+
+    program.limits = Limits::default();
+    program.poke(4, 99);
+    assert_eq!(program.run_until_blocked(), Ok(RunState::Halted));
+  }
+}
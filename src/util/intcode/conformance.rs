@@ -0,0 +1,198 @@
+// Conformance suite, every case of `conformance.txt` being run under every execution engine
+
+use super::{
+  fuzz::{self, Outcome, ENGINES},
+  RunState, Snapshot,
+};
+
+const FIXTURE: &str = include_str!("conformance.txt");
+
+// Instructions any case is allowed to run
+const LIMIT: u64 = 10_000;
+
+#[derive(Debug, Default, PartialEq)]
+struct Case {
+  name: String,
+  line: usize,
+  program: Vec<i128>,
+  inputs: Vec<i128>,
+  result: String,
+  outputs: Option<Vec<i128>>,
+  memory: Vec<(usize, i128)>,
+  pointer: Option<usize>,
+  base: Option<usize>,
+  executed: Option<u64>,
+}
+
+fn words(text: &str) -> Result<Vec<i128>, String> {
+  text
+    .split(',')
+    .filter(|word| !word.trim().is_empty())
+    .map(|word| {
+      word.trim().parse().map_err(|_| format!("invalid word `{}`", word))
+    })
+    .collect()
+}
+
+fn parse(fixture: &str) -> Result<Vec<Case>, String> {
+  let mut cases: Vec<Case> = vec![];
+
+  for (index, line) in fixture.lines().enumerate() {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+
+    let (field, value) = line.split_once(' ').unwrap_or((line, ""));
+    let error = |message: String| format!("line {}: {}", index + 1, message);
+
+    if field == "case" {
+      cases.push(Case {
+        name: value.to_string(),
+        line: index + 1,
+        ..Case::default()
+      });
+      continue;
+    }
+
+    let case = match cases.last_mut() {
+      Some(case) => case,
+      None => return Err(error(String::from("field outside of a case"))),
+    };
+
+    let number = |value: &str| {
+      value.parse().map_err(|_| error(format!("invalid number `{}`", value)))
+    };
+
+    match field {
+      "program" => case.program = words(value).map_err(error)?,
+      "inputs" => case.inputs = words(value).map_err(error)?,
+      "result" => case.result = value.to_string(),
+      "outputs" => case.outputs = Some(words(value).map_err(error)?),
+      "memory" => {
+        for cell in value.split_whitespace() {
+          let (address, word) = match cell.split_once('=') {
+            Some(pair) => pair,
+            None => return Err(error(format!("invalid cell `{}`", cell))),
+          };
+          let word = word
+            .parse()
+            .map_err(|_| error(format!("invalid word `{}`", word)))?;
+          case.memory.push((number(address)?, word));
+        }
+      }
+      "pointer" => case.pointer = Some(number(value)?),
+      "base" => case.base = Some(number(value)?),
+      "executed" => case.executed = Some(number(value)? as u64),
+      _ => return Err(error(format!("unknown field `{}`", field))),
+    }
+  }
+
+  Ok(cases)
+}
+
+fn read(snapshot: &Snapshot, address: usize) -> i128 {
+  match address.checked_sub(snapshot.stack.len()) {
+    None => snapshot.stack[address],
+    Some(address) => snapshot
+      .heap
+      .iter()
+      .find(|(cell, _)| *cell == address)
+      .map(|(_, value)| *value)
+      .unwrap_or_default(),
+  }
+}
+
+// Every expectation of the case the outcome does not meet
+fn mismatches(case: &Case, outcome: &Outcome) -> Vec<String> {
+  let (result, executed, snapshot) = match outcome {
+    Outcome::Finished { result, executed, snapshot } => {
+      (result, executed, snapshot)
+    }
+    Outcome::Panicked(message) => {
+      return vec![format!("panicked: {}", message)]
+    }
+  };
+
+  let result = match result {
+    Ok(RunState::Halted) => String::from("halted"),
+    Ok(RunState::AwaitingInput) => String::from("awaiting input"),
+    Ok(state) => format!("{:?}", state),
+    Err(err) => format!("error {}", err),
+  };
+
+  let mut mismatches = vec![];
+  let mut expect = |field: &str, expected: String, found: String| {
+    if expected != found {
+      mismatches
+        .push(format!("{}: expected {}, found {}", field, expected, found));
+    }
+  };
+
+  expect("result", case.result.clone(), result);
+
+  if let Some(outputs) = &case.outputs {
+    expect(
+      "outputs",
+      format!("{:?}", outputs),
+      format!("{:?}", snapshot.outputs),
+    );
+  }
+  for (address, value) in &case.memory {
+    let found = read(snapshot, *address);
+    expect(
+      &format!("memory at {}", address),
+      value.to_string(),
+      found.to_string(),
+    );
+  }
+  if let Some(pointer) = case.pointer {
+    expect("pointer", pointer.to_string(), snapshot.pointer.to_string());
+  }
+  if let Some(base) = case.base {
+    expect("base", base.to_string(), snapshot.base.to_string());
+  }
+  if let Some(count) = case.executed {
+    expect("executed", count.to_string(), executed.to_string());
+  }
+
+  mismatches
+}
+
+#[test]
+fn conformance() {
+  let cases = parse(FIXTURE).unwrap();
+  let mut failures = vec![];
+
+  for case in &cases {
+    for engine in ENGINES.iter() {
+      let outcome = fuzz::execute(engine, &case.program, &case.inputs, LIMIT);
+
+      for mismatch in mismatches(case, &outcome) {
+        failures.push(format!(
+          "{} (line {}, {}): {}",
+          case.name, case.line, engine, mismatch
+        ));
+      }
+    }
+  }
+
+  assert!(cases.len() > 150);
+  assert!(failures.is_empty(), "\n{}\n", failures.join("\n"));
+}
+
+#[test]
+fn fixture_errors() {
+  assert_eq!(
+    parse("program 1,2"),
+    Err(String::from("line 1: field outside of a case"))
+  );
+  assert_eq!(
+    parse("case a\nprogram 1,x"),
+    Err(String::from("line 2: invalid word `x`"))
+  );
+  assert_eq!(
+    parse("case a\nspeed 3"),
+    Err(String::from("line 2: unknown field `speed`"))
+  );
+}
@@ -0,0 +1,332 @@
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  io::{self, BufRead, Write},
+};
+
+use super::{disassemble_at, Program, RunState};
+
+const HELP: &str = "Commands:
+  s, step [N]           execute the next N instructions (defaults to 1)
+  n, next               execute until the next output
+  c, continue           execute until a breakpoint, a watchpoint or an input request
+  b, break ADDR         toggle a breakpoint on an instruction address
+  w, watch ADDR         toggle a watchpoint on a memory cell
+  r, regs               show the instruction pointer, relative base and state
+  m, mem ADDR [N]       dump N memory cells in use (defaults to 8)
+  l, list [ADDR] [N]    disassemble N instructions (defaults to the pointer and 8)
+  i, input VALUE...     push values to the input queue
+  q, quit               leave the debugger
+An empty line repeats the last command.";
+
+// Reason why execution was stopped while stepping
+enum Stop {
+  Output(i128),
+  Watchpoint(usize, i128, i128),
+  State(RunState),
+  Error(String),
+}
+
+pub struct Debugger {
+  program: Program,
+  breakpoints: BTreeSet<usize>,
+  // Watched memory cells, with their last known value
+  watchpoints: BTreeMap<usize, i128>,
+  state: RunState,
+}
+
+impl Debugger {
+  pub fn new(program: Program) -> Debugger {
+    Debugger {
+      program,
+      breakpoints: BTreeSet::new(),
+      watchpoints: BTreeMap::new(),
+      state: RunState::Running,
+    }
+  }
+
+  // Read commands from `reader` until it is exhausted or the user quits
+  pub fn repl<R, W>(&mut self, reader: R, mut writer: W) -> io::Result<()>
+  where
+    R: BufRead,
+    W: Write,
+  {
+    let mut last = String::new();
+
+    self.list(&mut writer, self.program.pointer(), 1)?;
+    write!(writer, "(intcode) ")?;
+    writer.flush()?;
+
+    for line in reader.lines() {
+      let mut line = line?.trim().to_string();
+      if line.is_empty() {
+        line = last.clone();
+      }
+
+      if !self.command(&line, &mut writer)? {
+        return Ok(());
+      }
+
+      last = line;
+      write!(writer, "(intcode) ")?;
+      writer.flush()?;
+    }
+
+    writeln!(writer)
+  }
+
+  // Execute a command, returning false when the debugger should be left
+  fn command<W: Write>(
+    &mut self, line: &str, writer: &mut W,
+  ) -> io::Result<bool> {
+    let mut args = line.split_whitespace();
+    let command = args.next().unwrap_or_default();
+    let args: Vec<usize> = args.filter_map(|arg| arg.parse().ok()).collect();
+
+    match (command, args.as_slice()) {
+      ("", _) => (),
+      ("s", _) | ("step", _) => {
+        let count = args.first().copied().unwrap_or(1);
+        for _ in 0..count {
+          if let Some(stop) = self.advance() {
+            self.report(writer, stop)?;
+            break;
+          }
+        }
+        self.list(writer, self.program.pointer(), 1)?;
+      }
+      ("n", _) | ("next", _) => self.resume(writer, true)?,
+      ("c", _) | ("continue", _) => self.resume(writer, false)?,
+      ("b", &[address]) | ("break", &[address]) => {
+        if !self.breakpoints.remove(&address) {
+          self.breakpoints.insert(address);
+        }
+        writeln!(writer, "breakpoints: {:?}", self.breakpoints)?;
+      }
+      ("w", &[address]) | ("watch", &[address]) => {
+        if self.watchpoints.remove(&address).is_none() {
+          self.watchpoints.insert(address, self.program.peek(address));
+        }
+        let watched: Vec<&usize> = self.watchpoints.keys().collect();
+        writeln!(writer, "watchpoints: {:?}", watched)?;
+      }
+      ("r", _) | ("regs", _) => writeln!(
+        writer,
+        "pointer: {}  base: {}  state: {:?}  inputs: {:?}",
+        self.program.pointer(),
+        self.program.base(),
+        self.state,
+        self.program.io.inputs
+      )?,
+      ("m", &[address]) | ("mem", &[address]) => {
+        self.dump(writer, address, 8)?
+      }
+      ("m", &[address, count]) | ("mem", &[address, count]) => {
+        self.dump(writer, address, count)?
+      }
+      ("l", &[]) | ("list", &[]) => {
+        self.list(writer, self.program.pointer(), 8)?
+      }
+      ("l", &[address]) | ("list", &[address]) => {
+        self.list(writer, address, 8)?
+      }
+      ("l", &[address, count]) | ("list", &[address, count]) => {
+        self.list(writer, address, count)?
+      }
+      ("i", _) | ("input", _) => {
+        // Inputs can be negative, so they are parsed again
+        for value in line.split_whitespace().skip(1) {
+          match value.parse::<i128>() {
+            Ok(value) => self.program.io.inputs.push_back(value),
+            Err(_) => writeln!(writer, "invalid input `{}`", value)?,
+          }
+        }
+        writeln!(writer, "inputs: {:?}", self.program.io.inputs)?;
+      }
+      ("q", _) | ("quit", _) => return Ok(false),
+      _ => writeln!(writer, "{}", HELP)?,
+    }
+
+    Ok(true)
+  }
+
+  // Execute until something noteworthy happens, stopping at breakpoints (except the one we are on)
+  fn resume<W: Write>(
+    &mut self, writer: &mut W, until_output: bool,
+  ) -> io::Result<()> {
+    let mut first = true;
+
+    loop {
+      if !first && self.breakpoints.contains(&self.program.pointer()) {
+        writeln!(writer, "breakpoint at {}", self.program.pointer())?;
+        break;
+      }
+      first = false;
+
+      match self.advance() {
+        Some(Stop::Output(value)) if !until_output => {
+          writeln!(writer, "output: {}", value)?
+        }
+        Some(stop) => {
+          self.report(writer, stop)?;
+          break;
+        }
+        None => (),
+      }
+    }
+
+    self.list(writer, self.program.pointer(), 1)
+  }
+
+  // Execute a single instruction, and tell whether the execution should be interrupted
+  fn advance(&mut self) -> Option<Stop> {
+    let stop = match self.program.step() {
+      Ok(RunState::Running) => None,
+      Ok(RunState::Output(value)) => Some(Stop::Output(value)),
+      Ok(state) => Some(Stop::State(state)),
+      Err(err) => Some(Stop::Error(err.to_string())),
+    };
+
+    self.state = match stop {
+      Some(Stop::State(state)) => state,
+      _ => RunState::Running,
+    };
+
+    if stop.is_some() {
+      return stop;
+    }
+
+    for (address, value) in self.watchpoints.iter_mut() {
+      let current = self.program.peek(*address);
+      if current != *value {
+        let previous = *value;
+        *value = current;
+        return Some(Stop::Watchpoint(*address, previous, current));
+      }
+    }
+
+    None
+  }
+
+  fn report<W: Write>(&self, writer: &mut W, stop: Stop) -> io::Result<()> {
+    match stop {
+      Stop::Output(value) => writeln!(writer, "output: {}", value),
+      Stop::Watchpoint(address, previous, current) => writeln!(
+        writer,
+        "watchpoint [{}]: {} -> {}",
+        address, previous, current
+      ),
+      Stop::State(RunState::AwaitingInput) => {
+        writeln!(writer, "awaiting input, use `input`")
+      }
+      Stop::State(state) => writeln!(writer, "{:?}", state),
+      Stop::Error(message) => writeln!(writer, "error: {}", message),
+    }
+  }
+
+  // Cells past the last one holding a value are left out
+  fn dump<W: Write>(
+    &self, writer: &mut W, address: usize, count: usize,
+  ) -> io::Result<()> {
+    let end = address.saturating_add(count).min(self.program.extent());
+
+    for address in address..end {
+      writeln!(writer, "{:04}:  {}", address, self.program.peek(address))?;
+    }
+
+    Ok(())
+  }
+
+  fn list<W: Write>(
+    &self, writer: &mut W, mut address: usize, count: usize,
+  ) -> io::Result<()> {
+    for _ in 0..count {
+      let line = disassemble_at(address, usize::MAX, |address| {
+        self.program.peek(address)
+      });
+      let marker = if address == self.program.pointer() { ">" } else { " " };
+
+      writeln!(writer, "{} {}", marker, line)?;
+
+      address = match address.checked_add(line.size()) {
+        Some(next) => next,
+        None => break,
+      };
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Debugger;
+  use crate::util::intcode::Program;
+
+  #[test]
+  fn session() {
+    // Adds 1 to every input and outputs it
+    let stack = vec![3, 11, 1001, 11, 1, 11, 4, 11, 1105, 1, 0, 0];
+    let commands = "b 6\nw 11\nc\ni 41\nc\nw 11\nc\n\nregs\nq\n";
+    let mut output = vec![];
+
+    Debugger::new(Program::new(stack, vec![]))
+      .repl(commands.as_bytes(), &mut output)
+      .unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<&str> = output
+      .lines()
+      .map(|line| line.trim_start_matches("(intcode) "))
+      .collect();
+
+    assert_eq!(
+      lines,
+      vec![
+        "> 0000:  Input [11]                               ; 3,11",
+        "breakpoints: {6}",
+        "watchpoints: [11]",
+        "awaiting input, use `input`",
+        "> 0000:  Input [11]                               ; 3,11",
+        "inputs: [41]",
+        "watchpoint [11]: 0 -> 41",
+        "> 0002:  Add [11], #1, [11]                       ; 1001,11,1,11",
+        "watchpoints: []",
+        "breakpoint at 6",
+        "> 0006:  Output [11]                              ; 4,11",
+        "output: 42",
+        "awaiting input, use `input`",
+        "> 0000:  Input [11]                               ; 3,11",
+        "pointer: 0  base: 0  state: AwaitingInput  inputs: []",
+        "",
+      ]
+    );
+  }
+
+  #[test]
+  fn large_arguments() {
+    let commands =
+      "m 1 18446744073709551615\nm 9 4\nl 18446744073709551615 3\nq\n";
+    let mut output = vec![];
+
+    Debugger::new(Program::new(vec![104, 7, 99], vec![]))
+      .repl(commands.as_bytes(), &mut output)
+      .unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    let lines: Vec<&str> = output
+      .lines()
+      .map(|line| line.trim_start_matches("(intcode) "))
+      .collect();
+
+    assert_eq!(
+      lines,
+      vec![
+        "> 0000:  Output #7                                ; 104,7",
+        "0001:  7",
+        "0002:  99",
+        "  18446744073709551615:  DATA 0                                   ; 0",
+        "",
+      ]
+    );
+  }
+}
@@ -0,0 +1,135 @@
+use std::fmt;
+
+use super::{instruction::Instruction, mode::Mode, Extensions};
+
+// A line of a disassembled listing, covering either a whole instruction or a single data word
+#[derive(Debug)]
+pub struct Line {
+  pub address: usize,
+  pub words: Vec<i128>,
+  instruction: Option<Instruction>,
+}
+
+impl Line {
+  pub fn size(&self) -> usize {
+    self.words.len()
+  }
+}
+
+// Render the line as `address: mnemonic operands ; raw words`
+//  * Position mode operands are rendered as `[address]`
+//  * Immediate mode operands are rendered as `#value`
+//  * Relative mode operands are rendered as `rb+offset`
+impl fmt::Display for Line {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    let text = match self.instruction {
+      Some(instruction) => {
+        let operands: Vec<String> = instruction
+          .op
+          .modes()
+          .iter()
+          .zip(instruction.params.iter())
+          .take(instruction.op.arity())
+          .map(|(mode, param)| operand(*mode, *param))
+          .collect();
+
+        format!("{} {}", instruction.op.name(), operands.join(", "))
+      }
+      None => format!("DATA {}", self.words[0]),
+    };
+
+    let words: Vec<String> =
+      self.words.iter().map(|word| word.to_string()).collect();
+
+    write!(
+      formatter,
+      "{:04}:  {:<40} ; {}",
+      self.address,
+      text.trim_end(),
+      words.join(",")
+    )
+  }
+}
+
+pub(super) fn operand(mode: Mode, value: i128) -> String {
+  match mode {
+    Mode::Position => format!("[{}]", value),
+    Mode::Immediate => format!("#{}", value),
+    Mode::Relative if value < 0 => format!("rb{}", value),
+    Mode::Relative => format!("rb+{}", value),
+  }
+}
+
+// Decode an image from start to end
+// Words that cannot be decoded, or that would not be encoded back to the same value, are listed as data
+pub fn disassemble(image: &[i128]) -> Vec<Line> {
+  let mut lines = vec![];
+  let mut address = 0;
+
+  while address < image.len() {
+    let line = disassemble_at(address, image.len(), |address| {
+      image.get(address).copied().unwrap_or_default()
+    });
+
+    address += line.size();
+    lines.push(line);
+  }
+
+  lines
+}
+
+// Decode the line starting at `address`, in a memory of `size` words read through `read`
+pub fn disassemble_at<F>(address: usize, size: usize, read: F) -> Line
+where
+  F: Fn(usize) -> i128,
+{
+  let instruction =
+    match Instruction::decode(address, &read, &Extensions::new()) {
+      Ok(instruction)
+        if instruction.next(address).is_some_and(|next| next <= size)
+          && is_canonical(instruction, read(address)) =>
+      {
+        Some(instruction)
+      }
+      _ => None,
+    };
+
+  let size = instruction.map(|instruction| instruction.size()).unwrap_or(1);
+  let words = (0..size).map(|offset| read(address + offset)).collect();
+
+  Line { address, words, instruction }
+}
+
+// Whether the listing of the instruction is enough to produce `word` again
+fn is_canonical(instruction: Instruction, word: i128) -> bool {
+  let unused = instruction.op.modes()[instruction.op.arity()..]
+    .iter()
+    .all(|mode| mode.value() == 0);
+
+  unused && instruction.op.encode() == word
+}
+
+#[cfg(test)]
+mod tests {
+  use super::disassemble;
+
+  #[test]
+  fn listing() {
+    let image = vec![3, 9, 1008, 9, -8, 9, 204, -1, 99, 0, 20004, 42];
+    let listing: Vec<String> =
+      disassemble(&image).iter().map(|line| line.to_string()).collect();
+
+    assert_eq!(
+      listing,
+      vec![
+        "0000:  Input [9]                                ; 3,9",
+        "0002:  IfEquals [9], #-8, [9]                   ; 1008,9,-8,9",
+        "0006:  Output rb-1                              ; 204,-1",
+        "0008:  Halt                                     ; 99",
+        "0009:  DATA 0                                   ; 0",
+        "0010:  DATA 20004                               ; 20004",
+        "0011:  DATA 42                                  ; 42",
+      ]
+    );
+  }
+}
@@ -0,0 +1,66 @@
+use std::{error::Error, fmt, io};
+
+use super::Limit;
+
+// Reason why a program was interrupted
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Reason {
+  // The operation code (last two digits of the instruction) is unknown
+  InvalidOpcode,
+  // One of the parameter mode digits is not a known mode
+  InvalidMode(i128),
+  // A parameter that is written to was given in immediate mode
+  ImmediateWrite,
+  // An input was requested, but the input queue was empty
+  MissingInput,
+  // An address was computed from a negative value
+  NegativeAddress,
+  // An arithmetic operation or an address does not fit in the word type
+  Overflow,
+  // Reading an input or writing an output failed
+  Io(io::ErrorKind),
+  // An extension asked to write a value, but its opcode has no parameter to write to
+  MissingTarget,
+  // The program ran out of its execution budget, it can be resumed once its limits are raised
+  LimitExceeded(Limit),
+}
+
+impl fmt::Display for Reason {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match self {
+      Reason::InvalidOpcode => write!(formatter, "invalid opcode"),
+      Reason::InvalidMode(mode) => write!(formatter, "invalid mode {}", mode),
+      Reason::ImmediateWrite => {
+        write!(formatter, "cannot write with mode immediate")
+      }
+      Reason::MissingInput => write!(formatter, "not enough inputs"),
+      Reason::NegativeAddress => write!(formatter, "negative address"),
+      Reason::Overflow => write!(formatter, "overflow"),
+      Reason::MissingTarget => write!(formatter, "no target to write to"),
+      Reason::Io(kind) => write!(formatter, "input/output error ({:?})", kind),
+      Reason::LimitExceeded(limit) => write!(formatter, "{}", limit),
+    }
+  }
+}
+
+// Error returned when a program cannot continue its execution
+//  * `pointer` is the address of the faulting instruction
+//  * `opcode` is the raw value found at that address
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct IntcodeError<W = i128> {
+  pub pointer: usize,
+  pub opcode: W,
+  pub reason: Reason,
+}
+
+impl<W: fmt::Display> fmt::Display for IntcodeError<W> {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    write!(
+      formatter,
+      "{} at address {} (opcode {})",
+      self.reason, self.pointer, self.opcode
+    )
+  }
+}
+
+impl<W: fmt::Debug + fmt::Display> Error for IntcodeError<W> {}
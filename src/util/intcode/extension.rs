@@ -0,0 +1,189 @@
+use std::{collections::HashMap, convert::TryFrom, fmt, sync::Arc};
+
+use super::{
+  mode::Mode,
+  opcode::{Custom, Opcode},
+  Reason,
+};
+
+// What an extended instruction does once its handler returns
+//  * Effect::Continue moves on to the next instruction
+//  * Effect::Write stores a value in the target parameter of the instruction
+//  * Effect::Jump changes the instruction pointer to the given address
+//  * Effect::Output emits a value, exactly like the Output instruction
+//  * Effect::Halt stops the program like the Halt instruction, an exit code can be kept in its `retval`
+#[derive(Debug, PartialEq, Clone)]
+pub enum Effect<W = i128> {
+  Continue,
+  Write(W),
+  Jump(W),
+  Output(W),
+  Halt(Option<W>),
+}
+
+// Handlers are given the operands of the instruction, resolved as for tracers: values for the parameters read,
+// and the address of the target parameter
+type Handler<W> = Arc<dyn Fn(&[W]) -> Result<Effect<W>, Reason> + Send + Sync>;
+
+// Opcodes added to the instruction set of a program
+// Unknown opcodes are looked up here before being reported as invalid
+pub struct Extensions<W = i128> {
+  opcodes: HashMap<i128, (Custom, Handler<W>)>,
+}
+
+impl<W> Extensions<W> {
+  pub fn new() -> Extensions<W> {
+    Extensions { opcodes: HashMap::new() }
+  }
+
+  // Register the `name` instruction as opcode `code`, taking `arity` parameters, `target` being the index of the one
+  // written to by Effect::Write
+  // Panics if the opcode is already in use, or if its description cannot be encoded
+  pub fn add<F>(
+    &mut self, code: i128, name: &'static str, arity: usize,
+    target: Option<usize>, handler: F,
+  ) where
+    F: Fn(&[W]) -> Result<Effect<W>, Reason> + Send + Sync + 'static,
+  {
+    assert!(code > 0 && code < 100, "opcode {} is out of range", code);
+    assert!(Opcode::try_from(code).is_err(), "opcode {} is built in", code);
+    assert!(!self.opcodes.contains_key(&code), "opcode {} is taken", code);
+    assert!(arity <= 3, "`{}` has too many parameters", name);
+    assert!(target.is_none_or(|index| index < arity), "invalid target");
+
+    let custom = Custom { name, code, arity, target };
+    self.opcodes.insert(code, (custom, Arc::new(handler)));
+  }
+
+  // Decode an instruction word using one of the registered opcodes
+  pub(super) fn decode(&self, code: i128) -> Result<Opcode, Reason> {
+    let custom = match self.opcodes.get(&(code % 100)) {
+      Some((custom, _)) => *custom,
+      None => return Err(Reason::InvalidOpcode),
+    };
+
+    Ok(Opcode::Extended(
+      custom,
+      Mode::try_from(code / 100 % 10)?,
+      Mode::try_from(code / 1000 % 10)?,
+      Mode::try_from(code / 10000 % 10)?,
+    ))
+  }
+
+  pub(super) fn handler(&self, code: i128) -> Option<Handler<W>> {
+    self.opcodes.get(&code).map(|(_, handler)| handler.clone())
+  }
+}
+
+impl<W> Default for Extensions<W> {
+  fn default() -> Extensions<W> {
+    Extensions::new()
+  }
+}
+
+impl<W> Clone for Extensions<W> {
+  fn clone(&self) -> Extensions<W> {
+    Extensions { opcodes: self.opcodes.clone() }
+  }
+}
+
+impl<W> fmt::Debug for Extensions<W> {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    let mut opcodes: Vec<(i128, &str)> = self
+      .opcodes
+      .iter()
+      .map(|(code, (custom, _))| (*code, custom.name))
+      .collect();
+    opcodes.sort_unstable();
+
+    formatter.debug_map().entries(opcodes).finish()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::{Arc, Mutex};
+
+  use super::{Effect, Extensions};
+  use crate::util::intcode::{IntcodeError, Program, Reason, RunState};
+
+  #[test]
+  fn extensions() {
+    let printed = Arc::new(Mutex::new(vec![]));
+    let log = printed.clone();
+    let mut extensions = Extensions::new();
+
+    // Debug print, doubling, exit code and a failing trap
+    extensions.add(20, "Print", 1, None, move |operands: &[i128]| {
+      log.lock().unwrap().push(operands[0]);
+      Ok(Effect::Continue)
+    });
+    extensions.add(21, "Double", 2, Some(1), |operands: &[i128]| {
+      Ok(Effect::Write(operands[0] * 2))
+    });
+    extensions.add(22, "Exit", 1, None, |operands: &[i128]| {
+      Ok(Effect::Halt(Some(operands[0])))
+    });
+    extensions.add(23, "Trap", 0, None, |_: &[i128]| Err(Reason::MissingInput));
+
+    let stack = vec![120, 7, 21, 10, 11, 4, 11, 122, 3, 0, 21, 0];
+    let mut program = Program::new(stack, vec![]);
+    program.extensions = extensions.clone();
+
+    assert_eq!(program.run_until_event(), Ok(RunState::Output(42)));
+    assert_eq!(program.run_until_event(), Ok(RunState::Halted));
+    assert_eq!(program.retval, Some(3));
+    assert_eq!(program.pointer(), 7);
+    assert_eq!(*printed.lock().unwrap(), vec![7]);
+
+    let mut program = Program::new(vec![23, 99], vec![]);
+    program.extensions = extensions;
+
+    let reason = Reason::MissingInput;
+    let error = IntcodeError { pointer: 0, opcode: 23, reason };
+    assert_eq!(program.execute(), Err(error));
+
+    let mut program = Program::new(vec![23, 99], vec![]);
+    let reason = Reason::InvalidOpcode;
+    let error = IntcodeError { pointer: 0, opcode: 23, reason };
+    assert_eq!(program.execute(), Err(error));
+  }
+
+  #[test]
+  fn resume_after_halt() {
+    let calls = Arc::new(Mutex::new(0));
+    let count = calls.clone();
+    let mut program = Program::new(vec![25, 99], vec![]);
+
+    program.extensions.add(25, "Stop", 0, None, move |_: &[i128]| {
+      *count.lock().unwrap() += 1;
+      Ok(Effect::Halt(None))
+    });
+
+    // The handler is not run again when the halted program is resumed
+    assert_eq!(program.run_until_event(), Ok(RunState::Halted));
+    assert_eq!(program.run_until_event(), Ok(RunState::Halted));
+    assert_eq!(program.step(), Ok(RunState::Halted));
+    assert_eq!(*calls.lock().unwrap(), 1);
+    assert_eq!(program.executed(), 1);
+  }
+
+  #[test]
+  fn missing_target() {
+    let mut program = Program::new(vec![24, 99], vec![]);
+    program
+      .extensions
+      .add(24, "Answer", 0, None, |_: &[i128]| Ok(Effect::Write(42)));
+
+    let reason = Reason::MissingTarget;
+    let error = IntcodeError { pointer: 0, opcode: 24, reason };
+    assert_eq!(program.execute(), Err(error));
+  }
+
+  #[test]
+  #[should_panic(expected = "opcode 4 is built in")]
+  fn built_in() {
+    Extensions::new()
+      .add(4, "Output", 1, None, |_: &[i128]| Ok(Effect::Continue));
+  }
+}
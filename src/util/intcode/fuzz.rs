@@ -0,0 +1,463 @@
+use std::{
+  any::Any,
+  convert::TryFrom,
+  fmt,
+  panic::{self, AssertUnwindSafe},
+};
+
+use super::{
+  opcode::Opcode, Engine, Event, Expr, IntcodeError, Limits, Linear, Memory,
+  Program, Queue, Reason, RunState, Snapshot, Sparse, Symbolic, SymbolicError,
+  Tracer,
+};
+
+// Configurations every generated program is run under, all expected to behave exactly the same
+//  * `interpreter` is the reference, with the default paged memory
+//  * `compiler` executes hot code through the block compiler
+//  * `sparse` keeps its heap in a sparse memory
+//  * `linear` keeps its heap in a linear memory, up to `CAP`
+//  * `traced` goes through the tracing path of the interpreter
+//  * `symbolic` runs the program with constant values only, through `Symbolic`
+pub const ENGINES: [&str; 6] =
+  ["interpreter", "compiler", "sparse", "linear", "traced", "symbolic"];
+
+// First address the `linear` configuration does not keep in its linear memory, generated programs writing far beyond
+const CAP: usize = 1 << 16;
+
+// What a program did when run under one of the configurations
+#[derive(Debug, PartialEq, Clone)]
+pub enum Outcome {
+  Finished {
+    result: Result<RunState, IntcodeError>,
+    executed: u64,
+    snapshot: Box<Snapshot>,
+  },
+  Panicked(String),
+}
+
+impl fmt::Display for Outcome {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match self {
+      Outcome::Finished { result, executed, snapshot } => {
+        match result {
+          Ok(state) => write!(formatter, "{:?}", state)?,
+          Err(err) => write!(formatter, "{}", err)?,
+        }
+        write!(
+          formatter,
+          " after {} instructions, pointer {}, outputs {:?}",
+          executed, snapshot.pointer, snapshot.outputs
+        )
+      }
+      Outcome::Panicked(message) => write!(formatter, "panicked: {}", message),
+    }
+  }
+}
+
+// Program on which the configurations did not agree, with what each of them did
+#[derive(Debug, Clone)]
+pub struct Divergence {
+  pub iteration: usize,
+  pub stack: Vec<i128>,
+  pub inputs: Vec<i128>,
+  pub outcomes: Vec<(&'static str, Outcome)>,
+}
+
+impl fmt::Display for Divergence {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    let join = |words: &[i128]| {
+      let words: Vec<String> = words.iter().map(i128::to_string).collect();
+      words.join(",")
+    };
+
+    writeln!(formatter, "divergence on program #{}", self.iteration)?;
+    writeln!(formatter, "program: {}", join(&self.stack))?;
+    writeln!(formatter, "inputs:  {}", join(&self.inputs))?;
+
+    for (engine, outcome) in &self.outcomes {
+      writeln!(formatter, "  {:<12} {}", engine, outcome)?;
+    }
+
+    Ok(())
+  }
+}
+
+// Xorshift generator, so runs can be reproduced from their seed
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+  fn new(seed: u64) -> Rng {
+    Rng((seed ^ 0x9e37_79b9_7f4a_7c15).max(1))
+  }
+
+  fn next(&mut self) -> u64 {
+    self.0 ^= self.0 >> 12;
+    self.0 ^= self.0 << 25;
+    self.0 ^= self.0 >> 27;
+    self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+  }
+
+  fn below(&mut self, count: usize) -> usize {
+    (self.next() % count as u64) as usize
+  }
+
+  // Value in `low..high`
+  fn range(&mut self, low: i128, high: i128) -> i128 {
+    low + (self.next() as i128) % (high - low)
+  }
+}
+
+// Generator of random programs, made of valid instructions with parameters biased towards interesting values
+#[derive(Debug, Clone)]
+pub struct Fuzzer {
+  rng: Rng,
+  // Approximate number of words of every program, and number of instructions it can run
+  pub size: usize,
+  pub limit: u64,
+}
+
+impl Fuzzer {
+  pub fn new(seed: u64) -> Fuzzer {
+    Fuzzer { rng: Rng::new(seed), size: 32, limit: 2000 }
+  }
+
+  // Run `iterations` random programs, stopping on the first divergence, once minimized
+  pub fn run(&mut self, iterations: usize) -> Result<(), Divergence> {
+    for iteration in 0..iterations {
+      let (stack, inputs) = (self.program(), self.inputs());
+
+      if check(&stack, &inputs, self.limit).is_some() {
+        let limit = self.limit;
+        let (stack, inputs) = minimize(stack, inputs, |stack, inputs| {
+          check(stack, inputs, limit).is_some()
+        });
+        let outcomes = check(&stack, &inputs, limit).unwrap_or_default();
+
+        return Err(Divergence { iteration, stack, inputs, outcomes });
+      }
+    }
+
+    Ok(())
+  }
+
+  // Instructions picked from the opcode table until the program is large enough, followed by Halt and some data
+  pub fn program(&mut self) -> Vec<i128> {
+    let mut stack = vec![];
+
+    while stack.len() < self.size {
+      let (_, code, arity) = Opcode::TABLE[self.rng.below(Opcode::TABLE.len())];
+      let target = Opcode::try_from(code).ok().and_then(Opcode::target);
+      let mut word = code;
+
+      for (index, factor) in [100, 1000, 10000].iter().take(arity).enumerate() {
+        // Written parameters are never immediate
+        let mode = match self.rng.below(3) {
+          1 if target == Some(index) => 0,
+          mode => mode as i128,
+        };
+        word += mode * factor;
+      }
+
+      stack.push(word);
+      for _ in 0..arity {
+        let param = self.param(self.size);
+        stack.push(param);
+      }
+    }
+
+    stack.push(99);
+    for _ in 0..self.rng.below(8) {
+      let value = self.rng.range(-16, 16);
+      stack.push(value);
+    }
+
+    stack
+  }
+
+  fn inputs(&mut self) -> Vec<i128> {
+    (0..self.rng.below(4)).map(|_| self.rng.range(-100, 100)).collect()
+  }
+
+  // Mostly addresses within the program, but also small and negative values, heap addresses, and values close to
+  // the limits of the word and address types
+  fn param(&mut self, size: usize) -> i128 {
+    const EXTREMES: [i128; 6] = [
+      i128::MAX,
+      i128::MIN,
+      i64::MAX as i128,
+      i64::MIN as i128,
+      1 << 64,
+      (1 << 64) - 1,
+    ];
+
+    match self.rng.below(20) {
+      0..=9 => self.rng.range(0, size as i128 + 8),
+      10..=13 => self.rng.range(-8, 8),
+      14..=16 => self.rng.range(0, 4096),
+      17 => self.rng.range(-4096, 0),
+      18 => EXTREMES[self.rng.below(EXTREMES.len())],
+      _ => self.rng.range(0, 1 << 40),
+    }
+  }
+}
+
+// Run a program under every configuration, returning what each did unless they all agree
+pub fn check(
+  stack: &[i128], inputs: &[i128], limit: u64,
+) -> Option<Vec<(&'static str, Outcome)>> {
+  let outcomes: Vec<(&'static str, Outcome)> = ENGINES
+    .iter()
+    .map(|engine| (*engine, execute(engine, stack, inputs, limit)))
+    .collect();
+
+  if outcomes.iter().all(|(_, outcome)| *outcome == outcomes[0].1) {
+    None
+  } else {
+    Some(outcomes)
+  }
+}
+
+pub(super) fn execute(
+  engine: &str, stack: &[i128], inputs: &[i128], limit: u64,
+) -> Outcome {
+  let (stack, inputs) = (stack.to_vec(), inputs.to_vec());
+
+  guard(|| match engine {
+    "compiler" => {
+      let mut program = Program::new(stack, inputs);
+      program.engine = Engine::Compiler;
+      outcome(program, limit)
+    }
+    "sparse" => {
+      let io = Queue::new(inputs);
+      outcome(Program::with_memory(stack, io, Sparse::default()), limit)
+    }
+    "linear" => {
+      let io = Queue::new(inputs);
+      outcome(Program::with_memory(stack, io, Capped::default()), limit)
+    }
+    "traced" => {
+      let program = Program::new(stack, inputs);
+      outcome(program.with_tracer(Vec::<Event>::new()), limit)
+    }
+    "symbolic" => symbolic(stack, inputs, limit),
+    _ => outcome(Program::new(stack, inputs), limit),
+  })
+}
+
+fn outcome<M, T>(mut program: Program<Queue, M, T>, limit: u64) -> Outcome
+where
+  M: Memory,
+  T: Tracer,
+{
+  program.limits = Limits::instructions(limit);
+
+  let result = program.run_until_blocked();
+  let executed = program.executed();
+
+  let snapshot = Box::new(program.snapshot());
+
+  Outcome::Finished { result, executed, snapshot }
+}
+
+// Run a program through `Symbolic`, and describe it as `Program` would once blocked
+fn symbolic(stack: Vec<i128>, inputs: Vec<i128>, limit: u64) -> Outcome {
+  let mut program = Symbolic::new(&stack);
+  program.inputs = inputs.into_iter().map(Expr::Const).collect();
+  program.limits = Limits::instructions(limit);
+
+  let result = match program.run() {
+    Ok(()) => Ok(RunState::Halted),
+    // `Program` waits for a missing input instead of failing
+    Err(SymbolicError::Program(err)) if err.reason == Reason::MissingInput => {
+      Ok(RunState::AwaitingInput)
+    }
+    Err(SymbolicError::Program(err)) => Err(err),
+    Err(err) => panic!("{}", err),
+  };
+
+  let outputs: Vec<i128> = program.outputs.iter().map(constant).collect();
+  let mut heap: Vec<(usize, i128)> = program
+    .heap()
+    .iter()
+    .map(|(address, value)| (*address, constant(value)))
+    .filter(|(_, value)| *value != 0)
+    .collect();
+  heap.sort_unstable();
+
+  let snapshot = Box::new(Snapshot {
+    pointer: program.pointer(),
+    base: program.base(),
+    stack: program.stack.iter().map(constant).collect(),
+    heap,
+    inputs: program.inputs.iter().map(constant).collect(),
+    retval: outputs.last().copied(),
+    outputs,
+    paused: false,
+    halted: result == Ok(RunState::Halted),
+    executed: program.executed(),
+  });
+
+  Outcome::Finished { result, executed: program.executed(), snapshot }
+}
+
+fn constant(value: &Expr) -> i128 {
+  match value {
+    Expr::Const(value) => *value,
+    value => panic!("symbolic value {} in a concrete program", value),
+  }
+}
+
+// Linear memory for the addresses below `CAP`, the others being kept aside so they do not all get allocated
+#[derive(Debug, Default)]
+struct Capped {
+  low: Linear,
+  high: Sparse,
+}
+
+impl Memory for Capped {
+  fn get(&self, address: usize) -> i128 {
+    if address < CAP {
+      self.low.get(address)
+    } else {
+      self.high.get(address)
+    }
+  }
+
+  fn set(&mut self, address: usize, value: i128) {
+    if address < CAP {
+      self.low.set(address, value)
+    } else {
+      self.high.set(address, value)
+    }
+  }
+
+  fn usage(&self) -> usize {
+    self.low.usage() + self.high.usage()
+  }
+
+  fn cells(&self) -> Vec<(usize, i128)> {
+    let mut cells = self.low.cells();
+    cells.extend(self.high.cells());
+    cells
+  }
+
+  fn extent(&self) -> usize {
+    match self.high.extent() {
+      0 => self.low.extent(),
+      extent => extent,
+    }
+  }
+}
+
+// Turn a panic into an outcome, so it is reported like any other divergence
+fn guard<F: FnOnce() -> Outcome>(run: F) -> Outcome {
+  match panic::catch_unwind(AssertUnwindSafe(run)) {
+    Ok(outcome) => outcome,
+    Err(payload) => Outcome::Panicked(message(payload)),
+  }
+}
+
+fn message(payload: Box<dyn Any + Send>) -> String {
+  match payload.downcast::<String>() {
+    Ok(message) => *message,
+    Err(payload) => match payload.downcast::<&str>() {
+      Ok(message) => message.to_string(),
+      Err(_) => String::from("unknown panic"),
+    },
+  }
+}
+
+// Shrink a program and its inputs for as long as `failing` still holds, by removing inputs and words, and by
+// bringing words closer to zero
+pub fn minimize<F>(
+  mut stack: Vec<i128>, mut inputs: Vec<i128>, mut failing: F,
+) -> (Vec<i128>, Vec<i128>)
+where
+  F: FnMut(&[i128], &[i128]) -> bool,
+{
+  loop {
+    let mut reduced = false;
+
+    for index in (0..inputs.len()).rev() {
+      let mut candidate = inputs.clone();
+      candidate.remove(index);
+
+      if failing(&stack, &candidate) {
+        inputs = candidate;
+        reduced = true;
+      }
+    }
+
+    for index in (0..stack.len()).rev() {
+      let mut candidate = stack.clone();
+      candidate.remove(index);
+
+      if failing(&candidate, &inputs) {
+        stack = candidate;
+        reduced = true;
+      }
+    }
+
+    for index in 0..stack.len() {
+      for value in [0, stack[index] / 2] {
+        if value == stack[index] {
+          continue;
+        }
+
+        let mut candidate = stack.clone();
+        candidate[index] = value;
+
+        if failing(&candidate, &inputs) {
+          stack = candidate;
+          reduced = true;
+          break;
+        }
+      }
+    }
+
+    if !reduced {
+      return (stack, inputs);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{check, minimize, Fuzzer};
+  use crate::util::intcode::Program;
+
+  #[test]
+  fn engines_agree() {
+    let mut fuzzer = Fuzzer::new(2019);
+
+    if let Err(divergence) = fuzzer.run(500) {
+      panic!("{}", divergence);
+    }
+
+    assert_eq!(check(&[1101, 1, 2, 0, 99], &[], 10), None);
+
+    // Writes far beyond the linear memory of the `linear` configuration
+    let stack = [1101, 40, 2, 1 << 40, 4, 1 << 40, 99];
+    assert_eq!(check(&stack, &[], 10), None);
+
+    // Fail on the address they write to, before reading their operands or overflowing
+    assert_eq!(check(&[2007, 0, -1, 1 << 64, 99], &[], 10), None);
+    assert_eq!(check(&[1101, i128::MAX, 1, -1, 99], &[], 10), None);
+  }
+
+  #[test]
+  fn minimization() {
+    let outputs_42 = |stack: &[i128], inputs: &[i128]| {
+      let mut program = Program::new(stack.to_vec(), inputs.to_vec());
+      let _ = program.run_until_blocked();
+      program.io.outputs.contains(&42)
+    };
+
+    let (stack, inputs) =
+      minimize(vec![104, 42, 99, 7, 7, 7], vec![1, 2], outputs_42);
+
+    assert_eq!((stack, inputs), (vec![104, 42], vec![]));
+  }
+}
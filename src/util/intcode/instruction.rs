@@ -0,0 +1,46 @@
+use std::convert::TryFrom;
+
+use super::{opcode::Opcode, Extensions, Reason, Word};
+
+// A whole decoded instruction: its opcode with the parameter modes, and the raw value of its parameters
+// Unused parameters are set to zero
+#[derive(Debug, Copy, Clone)]
+pub(super) struct Instruction<W = i128> {
+  pub op: Opcode,
+  pub params: [W; 3],
+}
+
+impl<W: Word> Instruction<W> {
+  // Decode the instruction starting at `address`, `read` being used to fetch memory words
+  // Opcodes that are not built in are looked up in `extensions`
+  pub fn decode<F>(
+    address: usize, mut read: F, extensions: &Extensions<W>,
+  ) -> Result<Instruction<W>, Reason>
+  where
+    F: FnMut(usize) -> W,
+  {
+    let code = read(address).to_i128().ok_or(Reason::InvalidOpcode)?;
+    let op = match Opcode::try_from(code) {
+      Err(Reason::InvalidOpcode) => extensions.decode(code)?,
+      op => op?,
+    };
+    let mut params = [W::zero(), W::zero(), W::zero()];
+
+    for (offset, param) in params.iter_mut().take(op.arity()).enumerate() {
+      let address = address.checked_add(offset + 1).ok_or(Reason::Overflow)?;
+      *param = read(address);
+    }
+
+    Ok(Instruction { op, params })
+  }
+
+  // Number of memory words used by the instruction
+  pub fn size(&self) -> usize {
+    self.op.arity() + 1
+  }
+
+  // Address following the instruction starting at `address`, unless it does not fit in memory
+  pub fn next(&self, address: usize) -> Option<usize> {
+    address.checked_add(self.size())
+  }
+}
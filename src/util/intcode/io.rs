@@ -0,0 +1,209 @@
+use std::{
+  collections::VecDeque,
+  io::{self, BufRead, Write},
+  sync::mpsc::{Receiver, Sender},
+};
+
+// Source of inputs and destination of outputs for a program
+pub trait IntcodeIo<W = i128> {
+  // Return the next input value, or `None` if none is available (yet)
+  fn read(&mut self) -> io::Result<Option<W>>;
+  // Receive a value output by the program
+  fn write(&mut self, value: W) -> io::Result<()>;
+  // The last value written was also handed back to the driver of the program, it does not need to be kept
+  fn handed_back(&mut self) {}
+}
+
+// In-memory queues, inputs are consumed from the front and outputs are kept until drained
+// Outputs already handed back to the driver (as `RunState::Output`) are not kept
+#[derive(Debug, Default, Clone)]
+pub struct Queue<W = i128> {
+  pub inputs: VecDeque<W>,
+  pub outputs: VecDeque<W>,
+}
+
+impl<W> Queue<W> {
+  pub fn new(inputs: Vec<W>) -> Queue<W> {
+    Queue { inputs: inputs.into(), outputs: VecDeque::new() }
+  }
+}
+
+impl<W> IntcodeIo<W> for Queue<W> {
+  fn read(&mut self) -> io::Result<Option<W>> {
+    Ok(self.inputs.pop_front())
+  }
+
+  fn write(&mut self, value: W) -> io::Result<()> {
+    self.outputs.push_back(value);
+    Ok(())
+  }
+
+  fn handed_back(&mut self) {
+    self.outputs.pop_back();
+  }
+}
+
+// Inputs and outputs are delegated to closures
+pub struct Closures<I, O> {
+  input: I,
+  output: O,
+}
+
+impl<I, O> Closures<I, O> {
+  pub fn new(input: I, output: O) -> Closures<I, O> {
+    Closures { input, output }
+  }
+}
+
+impl<W, I, O> IntcodeIo<W> for Closures<I, O>
+where
+  I: FnMut() -> Option<W>,
+  O: FnMut(W),
+{
+  fn read(&mut self) -> io::Result<Option<W>> {
+    Ok((self.input)())
+  }
+
+  fn write(&mut self, value: W) -> io::Result<()> {
+    (self.output)(value);
+    Ok(())
+  }
+}
+
+// Inputs and outputs go through channels, so programs can be wired across threads
+// Reading blocks until a value is received, and fails once every sender is gone
+#[derive(Debug)]
+pub struct Channel<W = i128> {
+  pub receiver: Receiver<W>,
+  pub sender: Sender<W>,
+}
+
+impl<W> Channel<W> {
+  pub fn new(receiver: Receiver<W>, sender: Sender<W>) -> Channel<W> {
+    Channel { receiver, sender }
+  }
+}
+
+impl<W> IntcodeIo<W> for Channel<W> {
+  fn read(&mut self) -> io::Result<Option<W>> {
+    match self.receiver.recv() {
+      Ok(value) => Ok(Some(value)),
+      Err(_) => Err(io::ErrorKind::BrokenPipe.into()),
+    }
+  }
+
+  fn write(&mut self, value: W) -> io::Result<()> {
+    self.sender.send(value).map_err(|_| io::ErrorKind::BrokenPipe.into())
+  }
+}
+
+// Inputs are parsed from a reader (separated by commas or whitespace), and outputs are written one per line
+#[derive(Debug)]
+pub struct Stream<R, W> {
+  reader: R,
+  writer: W,
+  pending: VecDeque<i128>,
+}
+
+impl<R, W> Stream<R, W>
+where
+  R: BufRead,
+  W: Write,
+{
+  pub fn new(reader: R, writer: W) -> Stream<R, W> {
+    Stream { reader, writer, pending: VecDeque::new() }
+  }
+}
+
+impl<R, W> IntcodeIo for Stream<R, W>
+where
+  R: BufRead,
+  W: Write,
+{
+  fn read(&mut self) -> io::Result<Option<i128>> {
+    while self.pending.is_empty() {
+      let mut line = String::new();
+      if self.reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+      }
+
+      for token in line.split(|c: char| c == ',' || c.is_whitespace()) {
+        if token.is_empty() {
+          continue;
+        }
+
+        match token.parse::<i128>() {
+          Ok(value) => self.pending.push_back(value),
+          Err(_) => return Err(io::ErrorKind::InvalidData.into()),
+        }
+      }
+    }
+
+    Ok(self.pending.pop_front())
+  }
+
+  fn write(&mut self, value: i128) -> io::Result<()> {
+    writeln!(self.writer, "{}", value)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Channel, Closures, Stream};
+  use crate::util::intcode::{Program, RunState};
+  use std::sync::mpsc;
+
+  #[test]
+  fn queue() {
+    // Outputs twice every input, until 0 is given
+    let stack =
+      vec![3, 15, 1006, 15, 14, 102, 2, 15, 15, 4, 15, 1105, 1, 0, 99, 0];
+    let mut program = Program::new(stack, vec![1, 2, 3]);
+
+    assert_eq!(program.run_until_blocked(), Ok(RunState::AwaitingInput));
+    assert_eq!(program.io.outputs, vec![2, 4, 6]);
+
+    // Outputs handed back to the caller are not kept
+    program.io.inputs.extend([4, 0]);
+
+    assert_eq!(program.run_until_event(), Ok(RunState::Output(8)));
+    assert_eq!(program.io.outputs, vec![2, 4, 6]);
+    assert_eq!(program.run_until_blocked(), Ok(RunState::Halted));
+  }
+
+  #[test]
+  fn closures() {
+    let mut inputs = vec![3, 2, 1];
+    let mut outputs = vec![];
+    let io = Closures::new(|| inputs.pop(), |value| outputs.push(value));
+    let mut program = Program::with_io(vec![3, 7, 4, 7, 1105, 1, 0, 0], io);
+
+    assert_eq!(program.run_until_blocked(), Ok(RunState::AwaitingInput));
+    drop(program);
+    assert_eq!(outputs, vec![1, 2, 3]);
+  }
+
+  #[test]
+  fn channel() {
+    let (input, receiver) = mpsc::channel();
+    let (sender, output) = mpsc::channel();
+    let io = Channel::new(receiver, sender);
+    let mut program = Program::with_io(vec![3, 0, 4, 0, 99], io);
+
+    input.send(42).unwrap();
+
+    assert_eq!(program.run_until_blocked(), Ok(RunState::Halted));
+    assert_eq!(output.recv(), Ok(42));
+  }
+
+  #[test]
+  fn stream() {
+    let mut output = vec![];
+    let io = Stream::new("1, 2\n3\n".as_bytes(), &mut output);
+    let mut program = Program::with_io(vec![3, 7, 4, 7, 1105, 1, 0, 0], io);
+
+    assert_eq!(program.run_until_blocked(), Ok(RunState::AwaitingInput));
+    drop(program);
+    assert_eq!(output, b"1\n2\n3\n");
+  }
+}
@@ -0,0 +1,214 @@
+use std::convert::TryInto;
+
+use super::{
+  mode::Mode,
+  opcode::{Opcode, Opcode::*},
+  Queue, RunState,
+};
+
+// The per-word state machine that `Program` used before instructions were decoded whole
+// It is only kept as a baseline for the benchmarks, and only supports valid programs
+
+#[derive(Debug)]
+enum State {
+  // New yet to be read instruction
+  Initial,
+  // Opcode and operation modes were read
+  Op(Opcode),
+  // Opcode, operation modes and at least one parameter were read
+  Params { op: Opcode, params: Vec<i128> },
+}
+
+#[derive(Debug)]
+pub(super) struct Program {
+  state: State,
+  pointer: usize,
+  base: usize,
+  heap: Vec<i128>,
+  pub stack: Vec<i128>,
+  pub io: Queue,
+}
+
+impl Program {
+  pub fn new(stack: Vec<i128>, inputs: Vec<i128>) -> Program {
+    Program {
+      state: State::Initial,
+      pointer: 0,
+      base: 0,
+      heap: vec![],
+      stack,
+      io: Queue::new(inputs),
+    }
+  }
+
+  // Read current instruction
+  fn next(&mut self, param: i128) {
+    self.state = match self.state {
+      // On clean state, the only thing that can be read is an opcode
+      State::Initial => State::Op(param.try_into().expect("invalid opcode")),
+
+      // When we get an opcode, the next thing to be read is a param (if applicable)
+      State::Op(ref op) => State::Params { op: *op, params: vec![param] },
+
+      // This state is a recursive one, adding new parameters until we get the proper count for the opcode
+      State::Params { op, ref params } => {
+        let mut params = params.to_vec();
+        params.push(param);
+        State::Params { op, params }
+      }
+    };
+  }
+
+  // Reset state machine when current instruction is finished
+  fn reset(&mut self) {
+    self.state = State::Initial;
+  }
+
+  // Loop over all instructions in memory until the program requires an interaction
+  pub fn run_until_event(&mut self) -> RunState {
+    loop {
+      match self.tick() {
+        RunState::Running => continue,
+        state => return state,
+      }
+    }
+  }
+
+  fn tick(&mut self) -> RunState {
+    // Get the instruction under the instruction pointer
+    let instruction = if self.stack.len() > self.pointer {
+      self.stack[self.pointer]
+    } else {
+      0
+    };
+
+    match self.state {
+      State::Initial => self.next(instruction),
+      State::Op(Halt(..)) => return RunState::Halted,
+      State::Op { .. } => self.next(instruction),
+
+      // If we have an opcode, we read its parameters until the branch for the appropriate opcode is satisfied with the number of parameters
+      State::Params { op: code, ref params } => match (code, params.as_slice())
+      {
+        (Add(m1, m2, m3), &[v1, v2, r]) => {
+          let (v1, v2, r) =
+            (self.deref(m1, v1), self.deref(m2, v2), self.address(m3, r));
+          self.set(r, v1 + v2);
+          self.reset();
+        }
+
+        (Mul(m1, m2, m3), &[v1, v2, r]) => {
+          let (v1, v2, r) =
+            (self.deref(m1, v1), self.deref(m2, v2), self.address(m3, r));
+          self.set(r, v1 * v2);
+          self.reset();
+        }
+
+        (Input(m1, ..), &[r]) => {
+          let r = self.address(m1, r);
+          let v = match self.io.inputs.pop_front() {
+            Some(v) => v,
+            None => return RunState::AwaitingInput,
+          };
+          self.set(r, v);
+          self.reset();
+        }
+
+        (Output(m, ..), &[v]) => {
+          let retval = self.deref(m, v);
+          self.reset();
+          return RunState::Output(retval);
+        }
+
+        (JumpIf(m1, m2, _), &[cond, address]) => {
+          if self.deref(m1, cond) != 0 {
+            self.pointer = self.deref(m2, address) as usize;
+          }
+          self.reset();
+        }
+
+        (JumpUnless(m1, m2, _), &[cond, address]) => {
+          if self.deref(m1, cond) == 0 {
+            self.pointer = self.deref(m2, address) as usize;
+          }
+          self.reset();
+        }
+
+        (IfLess(m1, m2, m3), &[v1, v2, r]) => {
+          let r = self.address(m3, r);
+          let v = self.deref(m1, v1) < self.deref(m2, v2);
+          self.set(r, v as i128);
+          self.reset();
+        }
+
+        (IfEquals(m1, m2, m3), &[v1, v2, r]) => {
+          let r = self.address(m3, r);
+          let v = self.deref(m1, v1) == self.deref(m2, v2);
+          self.set(r, v as i128);
+          self.reset();
+        }
+
+        (SetBase(m1, ..), &[v1]) => {
+          self.base = (self.base as i128 + self.deref(m1, v1)) as usize;
+          self.reset();
+        }
+
+        // We are missing some parameters for the current opcode, continue reading instructions
+        _ => self.next(instruction),
+      },
+    }
+
+    // Advance the instruction pointer, except when we are in a new instruction
+    if let State::Initial = self.state {
+    } else {
+      self.pointer += 1;
+    }
+
+    RunState::Running
+  }
+
+  fn deref(&mut self, mode: Mode, value: i128) -> i128 {
+    match mode {
+      Mode::Position => self.get(value as usize),
+      Mode::Immediate => value,
+      Mode::Relative => self.get((self.base as i128 + value) as usize),
+    }
+  }
+
+  fn address(&mut self, mode: Mode, address: i128) -> usize {
+    match mode {
+      Mode::Position => address as usize,
+      Mode::Relative => (self.base as i128 + address) as usize,
+      Mode::Immediate => panic!("immediate write"),
+    }
+  }
+
+  fn get(&mut self, address: usize) -> i128 {
+    if address >= self.stack.len() {
+      let offset = address - self.stack.len() + 1;
+
+      // The heap starts empty, if we are trying to write to a non-existing chunk of memory, we first need to extend the heap to accomodate for this new value
+      if offset > self.heap.len() {
+        self.heap.resize_with(offset, Default::default);
+      }
+
+      self.heap[offset - 1]
+    } else {
+      self.stack[address]
+    }
+  }
+
+  fn set(&mut self, address: usize, value: i128) {
+    if address >= self.stack.len() {
+      let offset = address - self.stack.len() + 1;
+
+      if offset > self.heap.len() {
+        self.heap.resize_with(offset, Default::default);
+      }
+
+      self.heap[offset - 1] = value;
+    } else {
+      self.stack[address] = value;
+    }
+  }
+}
@@ -0,0 +1,76 @@
+use std::{
+  fmt,
+  time::{Duration, Instant},
+};
+
+// Bounds on the execution of a program, unbounded by default
+//  * `instructions` is the total number of instructions the program can execute
+//  * `duration` is the time the program can run for, counted from its first instruction
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct Limits {
+  pub instructions: Option<u64>,
+  pub duration: Option<Duration>,
+}
+
+impl Limits {
+  pub fn instructions(count: u64) -> Limits {
+    Limits { instructions: Some(count), ..Limits::default() }
+  }
+
+  pub fn duration(duration: Duration) -> Limits {
+    Limits { duration: Some(duration), ..Limits::default() }
+  }
+}
+
+// Progress of a program against its limits: time of its first instruction, and count at which the clock is read next
+#[derive(Debug, Default, Copy, Clone)]
+pub(super) struct Budget {
+  started: Option<Instant>,
+  clock: u64,
+}
+
+impl Budget {
+  // Limit reached by a program that executed `executed` instructions so far
+  pub fn exceeded(&mut self, limits: &Limits, executed: u64) -> Option<Limit> {
+    if let Some(count) = limits.instructions {
+      if executed >= count {
+        return Some(Limit::Instructions(count));
+      }
+    }
+
+    if let Some(duration) = limits.duration {
+      let started = *self.started.get_or_insert_with(Instant::now);
+
+      // Reading the clock is much slower than executing an instruction
+      if executed >= self.clock {
+        self.clock = executed + 1024;
+
+        if started.elapsed() >= duration {
+          return Some(Limit::Duration(duration));
+        }
+      }
+    }
+
+    None
+  }
+}
+
+// Limit that was reached by a program
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Limit {
+  Instructions(u64),
+  Duration(Duration),
+}
+
+impl fmt::Display for Limit {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match self {
+      Limit::Instructions(count) => {
+        write!(formatter, "limit of {} instructions exceeded", count)
+      }
+      Limit::Duration(duration) => {
+        write!(formatter, "time limit of {:?} exceeded", duration)
+      }
+    }
+  }
+}
@@ -0,0 +1,113 @@
+use std::{
+  error::Error,
+  fmt, fs,
+  io::{self, Read},
+  path::Path,
+};
+
+// Reason why an image could not be loaded
+//  * LoadError::Io means the image could not be read
+//  * LoadError::InvalidToken means a value is not a number, `index` being its position in the image and `offset` the byte it starts at
+#[derive(Debug, PartialEq, Clone)]
+pub enum LoadError {
+  Io(io::ErrorKind),
+  InvalidToken { index: usize, offset: usize, token: String },
+}
+
+impl fmt::Display for LoadError {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match self {
+      LoadError::Io(kind) => {
+        write!(formatter, "could not read the image ({:?})", kind)
+      }
+      LoadError::InvalidToken { index, offset, token } => write!(
+        formatter,
+        "invalid value `{}` at index {} (byte {})",
+        token, index, offset
+      ),
+    }
+  }
+}
+
+impl Error for LoadError {}
+
+impl From<io::Error> for LoadError {
+  fn from(err: io::Error) -> LoadError {
+    LoadError::Io(err.kind())
+  }
+}
+
+// Load an image from a file
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<i128>, LoadError> {
+  load_str(&fs::read_to_string(path)?)
+}
+
+pub fn load_reader<R: Read>(mut reader: R) -> Result<Vec<i128>, LoadError> {
+  let mut source = String::new();
+  reader.read_to_string(&mut source)?;
+
+  load_str(&source)
+}
+
+// Parse comma-separated values, surrounded by any whitespace
+// The last value can be followed by a comma, but empty values are rejected anywhere else
+pub fn load_str(source: &str) -> Result<Vec<i128>, LoadError> {
+  let tokens: Vec<&str> = source.split(',').collect();
+  let mut image = Vec::with_capacity(tokens.len());
+  let mut offset = 0;
+
+  for (index, raw) in tokens.iter().enumerate() {
+    let token = raw.trim();
+    let start = offset + raw.len() - raw.trim_start().len();
+    offset += raw.len() + 1;
+
+    if token.is_empty() && index == tokens.len() - 1 {
+      break;
+    }
+
+    match token.parse() {
+      Ok(value) => image.push(value),
+      Err(_) => {
+        let token = token.to_string();
+        return Err(LoadError::InvalidToken { index, offset: start, token });
+      }
+    }
+  }
+
+  Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{load, load_reader, load_str, LoadError};
+
+  #[test]
+  fn parse() {
+    assert_eq!(load_str("1,2,-3"), Ok(vec![1, 2, -3]));
+    assert_eq!(load_str(" 1,\n2 ,\t3,\n"), Ok(vec![1, 2, 3]));
+    assert_eq!(load_str(""), Ok(vec![]));
+    assert_eq!(load_reader("104,1,99".as_bytes()), Ok(vec![104, 1, 99]));
+    assert_eq!(load("inputs/2019/d02.txt").map(|image| image.len()), Ok(149));
+  }
+
+  #[test]
+  fn errors() {
+    let data = vec![
+      ("1,2,x", 2, 4, "x"),
+      ("1,\n  2a ,3", 1, 5, "2a"),
+      ("1,,3", 1, 2, ""),
+      ("1 2", 0, 0, "1 2"),
+    ];
+
+    for (source, index, offset, token) in data {
+      let token = token.to_string();
+
+      assert_eq!(
+        load_str(source),
+        Err(LoadError::InvalidToken { index, offset, token })
+      );
+    }
+
+    assert!(matches!(load("inputs/2019/d99.txt"), Err(LoadError::Io(_))));
+  }
+}
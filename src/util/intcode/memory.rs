@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+
+use super::Word;
+
+// Extended memory of a program, used for every address beyond its initial stack
+// Addresses are given relative to the end of the stack, and memory that was never written reads as zero
+pub trait Memory<W = i128> {
+  fn get(&self, address: usize) -> W;
+  fn set(&mut self, address: usize, value: W);
+  // Number of memory words currently allocated
+  fn usage(&self) -> usize;
+  // Every non-zero word, ordered by address
+  fn cells(&self) -> Vec<(usize, W)>;
+  // Address following the last non-zero word
+  fn extent(&self) -> usize;
+}
+
+// Contiguous memory, grown up to the highest address written to
+#[derive(Debug, Default, Clone)]
+pub struct Linear<W = i128> {
+  words: Vec<W>,
+}
+
+impl<W: Word> Memory<W> for Linear<W> {
+  fn get(&self, address: usize) -> W {
+    self.words.get(address).cloned().unwrap_or_else(W::zero)
+  }
+
+  fn set(&mut self, address: usize, value: W) {
+    // If we are trying to write to a non-existing chunk of memory, we first need to extend it to accomodate for this new value
+    if address >= self.words.len() {
+      self.words.resize_with(address + 1, W::zero);
+    }
+
+    self.words[address] = value;
+  }
+
+  fn usage(&self) -> usize {
+    self.words.len()
+  }
+
+  fn cells(&self) -> Vec<(usize, W)> {
+    self
+      .words
+      .iter()
+      .enumerate()
+      .filter(|(_, value)| !value.is_zero())
+      .map(|(address, value)| (address, value.clone()))
+      .collect()
+  }
+
+  fn extent(&self) -> usize {
+    match self.words.iter().rposition(|value| !value.is_zero()) {
+      Some(address) => address + 1,
+      None => 0,
+    }
+  }
+}
+
+const PAGE_SIZE: usize = 1024;
+
+// Memory allocated by fixed-size pages, only when a page is first written to
+#[derive(Debug, Default, Clone)]
+pub struct Paged<W = i128> {
+  pages: HashMap<usize, Box<[W]>>,
+}
+
+impl<W: Word> Memory<W> for Paged<W> {
+  fn get(&self, address: usize) -> W {
+    match self.pages.get(&(address / PAGE_SIZE)) {
+      Some(page) => page[address % PAGE_SIZE].clone(),
+      None => W::zero(),
+    }
+  }
+
+  fn set(&mut self, address: usize, value: W) {
+    let page = address / PAGE_SIZE;
+
+    // Writing zero to a page that does not exist yet would not change anything
+    if value.is_zero() && !self.pages.contains_key(&page) {
+      return;
+    }
+
+    let page = self
+      .pages
+      .entry(page)
+      .or_insert_with(|| vec![W::zero(); PAGE_SIZE].into_boxed_slice());
+    page[address % PAGE_SIZE] = value;
+  }
+
+  fn usage(&self) -> usize {
+    self.pages.len() * PAGE_SIZE
+  }
+
+  fn cells(&self) -> Vec<(usize, W)> {
+    let mut pages: Vec<_> = self.pages.iter().collect();
+    pages.sort_by_key(|(index, _)| **index);
+
+    pages
+      .into_iter()
+      .flat_map(|(index, page)| {
+        page.iter().enumerate().filter(|(_, value)| !value.is_zero()).map(
+          move |(offset, value)| (index * PAGE_SIZE + offset, value.clone()),
+        )
+      })
+      .collect()
+  }
+
+  fn extent(&self) -> usize {
+    let mut indexes: Vec<usize> = self.pages.keys().copied().collect();
+    indexes.sort_unstable();
+
+    // Pages are only scanned from the highest one, until a non-zero word is found
+    for index in indexes.into_iter().rev() {
+      if let Some(offset) =
+        self.pages[&index].iter().rposition(|value| !value.is_zero())
+      {
+        return index * PAGE_SIZE + offset + 1;
+      }
+    }
+
+    0
+  }
+}
+
+// Memory storing every non-zero word individually
+#[derive(Debug, Default, Clone)]
+pub struct Sparse<W = i128> {
+  words: HashMap<usize, W>,
+}
+
+impl<W: Word> Memory<W> for Sparse<W> {
+  fn get(&self, address: usize) -> W {
+    self.words.get(&address).cloned().unwrap_or_else(W::zero)
+  }
+
+  fn set(&mut self, address: usize, value: W) {
+    if value.is_zero() {
+      self.words.remove(&address);
+    } else {
+      self.words.insert(address, value);
+    }
+  }
+
+  fn usage(&self) -> usize {
+    self.words.len()
+  }
+
+  fn cells(&self) -> Vec<(usize, W)> {
+    let mut cells: Vec<(usize, W)> = self
+      .words
+      .iter()
+      .map(|(address, value)| (*address, value.clone()))
+      .collect();
+    cells.sort_by_key(|(address, _)| *address);
+    cells
+  }
+
+  fn extent(&self) -> usize {
+    match self.words.keys().max() {
+      Some(address) => address + 1,
+      None => 0,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Linear, Memory, Paged, Sparse, PAGE_SIZE};
+  use crate::util::intcode::{Program, Queue};
+
+  #[test]
+  fn huge_addresses() {
+    // Stores 42 at address 10^12, and outputs it back
+    let stack = vec![1101, 40, 2, 1_000_000_000_000, 4, 1_000_000_000_000, 99];
+
+    let mut program = Program::new(stack.clone(), vec![]);
+    assert_eq!(program.execute_for_output(), Ok(Some(42)));
+    assert_eq!(program.memory_usage(), stack.len() + PAGE_SIZE);
+
+    let mut program =
+      Program::with_memory(stack.clone(), Queue::default(), Sparse::default());
+    assert_eq!(program.execute_for_output(), Ok(Some(42)));
+    assert_eq!(program.memory_usage(), stack.len() + 1);
+  }
+
+  #[test]
+  fn backends() {
+    let stack = vec![
+      109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+    ];
+
+    let mut program =
+      Program::with_memory(stack.clone(), Queue::default(), Linear::default());
+    program.run_until_blocked().unwrap();
+    assert_eq!(program.io.outputs, stack);
+    assert_eq!(program.memory_usage(), 102);
+
+    let mut program =
+      Program::with_memory(stack.clone(), Queue::default(), Sparse::default());
+    program.run_until_blocked().unwrap();
+    assert_eq!(program.io.outputs, stack);
+    assert_eq!(program.memory_usage(), stack.len() + 2);
+
+    let mut program =
+      Program::with_memory(stack.clone(), Queue::default(), Paged::default());
+    program.run_until_blocked().unwrap();
+    assert_eq!(program.io.outputs, stack);
+  }
+
+  #[test]
+  fn extent() {
+    fn check<M: Memory>(mut memory: M) {
+      assert_eq!(memory.extent(), 0);
+
+      // Words set back to zero are not in use anymore
+      memory.set(5, 1);
+      memory.set(PAGE_SIZE + 10, 3);
+      memory.set(PAGE_SIZE + 10, 0);
+      assert_eq!(memory.extent(), 6);
+    }
+
+    check(Linear::default());
+    check(Paged::default());
+    check(Sparse::default());
+
+    let mut program = Program::new(vec![1101, 40, 2, 20, 99], vec![]);
+    assert_eq!(program.extent(), 5);
+    program.run_until_blocked().unwrap();
+    assert_eq!(program.extent(), 21);
+  }
+}
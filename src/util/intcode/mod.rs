@@ -1,8 +1,55 @@
 #[macro_use]
 mod opcode;
+mod analysis;
+mod ascii;
+mod asm;
+#[cfg(test)]
+mod bench;
 mod circuit;
+mod compiler;
+#[cfg(test)]
+mod conformance;
+mod debugger;
+mod disasm;
+mod error;
+mod extension;
+mod fuzz;
+mod instruction;
+mod io;
+#[cfg(test)]
+mod legacy;
+mod limits;
+mod load;
+mod memory;
 mod mode;
+mod network;
+mod profile;
 mod program;
+mod session;
+mod snapshot;
+mod symbolic;
+mod trace;
+mod word;
 
-pub use circuit::Circuit;
-pub use program::Program;
+pub use analysis::{analyze, Analysis, Block};
+pub use ascii::Ascii;
+pub use asm::{assemble, AssemblyError};
+pub use circuit::{Circuit, CircuitError};
+pub use compiler::Engine;
+pub use debugger::Debugger;
+pub use disasm::{disassemble, disassemble_at, Line};
+pub use error::{IntcodeError, Reason};
+pub use extension::{Effect, Extensions};
+pub use fuzz::{check, minimize, Divergence, Fuzzer, Outcome, ENGINES};
+pub use io::{Channel, Closures, IntcodeIo, Queue, Stream};
+pub use limits::{Limit, Limits};
+pub use load::{load, load_reader, load_str, LoadError};
+pub use memory::{Linear, Memory, Paged, Sparse};
+pub use network::{Network, NetworkError, NetworkState, NodeId, Route};
+pub use profile::Profiler;
+pub use program::{ExitStatus, Program, RunState};
+pub use session::{Exchange, ReplayError, Session};
+pub use snapshot::Snapshot;
+pub use symbolic::{solve, Affine, Expr, Symbolic, SymbolicError};
+pub use trace::{Event, TraceWriter, Tracer};
+pub use word::Word;
@@ -1,3 +1,7 @@
+use std::convert::TryFrom;
+
+use super::Reason;
+
 #[derive(Debug, Copy, Clone)]
 pub(super) enum Mode {
   Immediate,
@@ -5,13 +9,25 @@ pub(super) enum Mode {
   Relative,
 }
 
-impl From<i128> for Mode {
-  fn from(mode: i128) -> Self {
+impl Mode {
+  pub(super) fn value(self) -> i128 {
+    match self {
+      Mode::Position => 0,
+      Mode::Immediate => 1,
+      Mode::Relative => 2,
+    }
+  }
+}
+
+impl TryFrom<i128> for Mode {
+  type Error = Reason;
+
+  fn try_from(mode: i128) -> Result<Self, Reason> {
     match mode {
-      0 => Mode::Position,
-      1 => Mode::Immediate,
-      2 => Mode::Relative,
-      _ => panic!("invalid mode"),
+      0 => Ok(Mode::Position),
+      1 => Ok(Mode::Immediate),
+      2 => Ok(Mode::Relative),
+      _ => Err(Reason::InvalidMode(mode)),
     }
   }
 }
@@ -0,0 +1,384 @@
+use std::{error::Error, fmt};
+
+use super::{IntcodeError, Program};
+
+pub type NodeId = usize;
+
+// How the outputs of a node are dispatched, a node can have many routes to fan its outputs out
+//  * Route::Wire sends every output to a node
+//  * Route::Slot groups outputs in frames of `size` values, and only sends the value at `index` of every frame
+//  * Route::Packets groups outputs in packets of `size` values, the first one being the address of the node receiving the rest
+#[derive(Debug, Copy, Clone)]
+pub enum Route {
+  Wire(NodeId),
+  Slot { size: usize, index: usize, to: NodeId },
+  Packets(usize),
+}
+
+enum Kind {
+  Program(Box<Program>),
+  // Forwards every value as soon as it is received
+  Hub,
+  // Keeps the last packet of `size` values it received, and only sends it when woken up
+  Nat { size: usize, received: Vec<i128>, packet: Option<Vec<i128>> },
+}
+
+struct Node {
+  name: String,
+  address: i128,
+  kind: Kind,
+  routes: Vec<Route>,
+  // Every value emitted by the node
+  log: Vec<i128>,
+}
+
+// State of a network after it was run
+//  * NetworkState::Halted means every program of the network reached its Halt instruction
+//  * NetworkState::Idle means every running program is waiting for an input no other node will send
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum NetworkState {
+  Halted,
+  Idle,
+}
+
+// Error of one of the programs of a network
+#[derive(Debug, PartialEq, Clone)]
+pub struct NetworkError {
+  pub node: String,
+  pub error: IntcodeError,
+}
+
+impl fmt::Display for NetworkError {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    write!(formatter, "node {}: {}", self.node, self.error)
+  }
+}
+
+impl Error for NetworkError {}
+
+// Nodes running programs and exchanging values through routes
+// Nodes are scheduled in the order they were added, each program running until it is blocked on an input
+#[derive(Default)]
+pub struct Network {
+  nodes: Vec<Node>,
+}
+
+impl Network {
+  pub fn new() -> Network {
+    Network::default()
+  }
+
+  fn add(&mut self, name: &str, kind: Kind) -> NodeId {
+    let id = self.nodes.len();
+
+    self.nodes.push(Node {
+      name: name.to_string(),
+      address: id as i128,
+      kind,
+      routes: vec![],
+      log: vec![],
+    });
+
+    id
+  }
+
+  // Add a node running `program`, its address being its identifier
+  pub fn add_program(&mut self, name: &str, program: Program) -> NodeId {
+    self.add(name, Kind::Program(Box::new(program)))
+  }
+
+  // Add a node forwarding every value it receives to its routes
+  // A value coming back to a hub it already went through is dropped, so cycles of hubs do not forward it forever
+  pub fn add_hub(&mut self, name: &str) -> NodeId {
+    self.add(name, Kind::Hub)
+  }
+
+  // Add a node keeping the last packet of `size` values it received, sent again every time the network is woken up
+  pub fn add_nat(&mut self, name: &str, size: usize) -> NodeId {
+    assert!(size > 0, "packets of `{}` cannot be empty", name);
+
+    let kind = Kind::Nat { size, received: vec![], packet: None };
+    self.add(name, kind)
+  }
+
+  // Change the address packets are sent to for a node
+  pub fn set_address(&mut self, node: NodeId, address: i128) {
+    self.nodes[node].address = address;
+  }
+
+  // Dispatch the outputs of a node through `route`, the frames and packets of which cannot be empty
+  pub fn connect(&mut self, from: NodeId, route: Route) {
+    match route {
+      Route::Slot { size: 0, .. } => panic!("slots of a frame cannot be empty"),
+      Route::Slot { size, index, .. } if index >= size => {
+        panic!("slot {} is outside of frames of {} values", index, size)
+      }
+      Route::Packets(0) => panic!("packets cannot be empty"),
+      _ => (),
+    }
+
+    self.nodes[from].routes.push(route);
+  }
+
+  // Queue values for a node, as if they were sent by another node
+  pub fn send(&mut self, to: NodeId, values: &[i128]) {
+    self.deliver(to, values, &mut vec![]);
+  }
+
+  // Send values to a node, `hubs` being the hubs they already went through
+  fn deliver(&mut self, to: NodeId, values: &[i128], hubs: &mut Vec<NodeId>) {
+    match &mut self.nodes[to].kind {
+      Kind::Program(program) => program.io.inputs.extend(values),
+      Kind::Hub if hubs.contains(&to) => (),
+      Kind::Hub => {
+        hubs.push(to);
+        for value in values {
+          self.emit(to, *value, hubs);
+        }
+        hubs.pop();
+      }
+      Kind::Nat { size, received, packet } => {
+        received.extend(values);
+
+        while received.len() >= *size {
+          *packet = Some(received.drain(..*size).collect());
+        }
+      }
+    }
+  }
+
+  // Run every node in turn until all programs halted, or until none of them can make any progress
+  pub fn run(&mut self) -> Result<NetworkState, NetworkError> {
+    loop {
+      let mut progress = false;
+
+      for id in 0..self.nodes.len() {
+        let node = &mut self.nodes[id];
+        let outputs: Vec<i128> = match &mut node.kind {
+          Kind::Program(program) if program.halted => vec![],
+          Kind::Program(program) => {
+            let executed = program.executed();
+
+            if let Err(error) = program.run_until_blocked() {
+              let node = node.name.clone();
+              return Err(NetworkError { node, error });
+            }
+
+            progress |= program.executed() != executed;
+            program.io.outputs.drain(..).collect()
+          }
+          // Other nodes only send values when they receive some
+          _ => vec![],
+        };
+
+        for value in outputs {
+          self.emit(id, value, &mut vec![]);
+        }
+      }
+
+      if self.nodes.iter().all(Node::halted) {
+        return Ok(NetworkState::Halted);
+      }
+      if !progress {
+        return Ok(NetworkState::Idle);
+      }
+    }
+  }
+
+  // Have every NAT node send its last packet, telling whether any was sent
+  pub fn wake(&mut self) -> bool {
+    let mut woken = false;
+
+    for id in 0..self.nodes.len() {
+      if let Kind::Nat { packet: Some(packet), .. } = &self.nodes[id].kind {
+        for value in packet.clone() {
+          self.emit(id, value, &mut vec![]);
+        }
+        woken = true;
+      }
+    }
+
+    woken
+  }
+
+  fn emit(&mut self, from: NodeId, value: i128, hubs: &mut Vec<NodeId>) {
+    let node = &mut self.nodes[from];
+    let position = node.log.len();
+
+    node.log.push(value);
+
+    for route in node.routes.clone() {
+      match route {
+        Route::Wire(to) => self.deliver(to, &[value], hubs),
+        Route::Slot { size, index, to } if position % size == index => {
+          self.deliver(to, &[value], hubs)
+        }
+        Route::Packets(size) if (position + 1).is_multiple_of(size) => {
+          let packet = self.nodes[from].log[position + 1 - size..].to_vec();
+          let to = self.nodes.iter().position(|node| node.address == packet[0]);
+
+          // Packets sent to unknown addresses are dropped
+          if let Some(to) = to {
+            self.deliver(to, &packet[1..], hubs);
+          }
+        }
+        _ => (),
+      }
+    }
+  }
+
+  pub fn program(&self, node: NodeId) -> Option<&Program> {
+    match &self.nodes[node].kind {
+      Kind::Program(program) => Some(program),
+      _ => None,
+    }
+  }
+
+  // Every value emitted by a node
+  pub fn log(&self, node: NodeId) -> &[i128] {
+    &self.nodes[node].log
+  }
+
+  // Name and output log of every node
+  pub fn logs(&self) -> Vec<(&str, &[i128])> {
+    let logs = self.nodes.iter();
+    logs.map(|node| (node.name.as_str(), node.log.as_slice())).collect()
+  }
+}
+
+impl Node {
+  // Whether the node cannot do anything anymore, nodes that are not programs never halt by themselves
+  fn halted(&self) -> bool {
+    match &self.kind {
+      Kind::Program(program) => program.halted,
+      _ => true,
+    }
+  }
+}
+
+// Report of the output log of every node, one per line
+impl fmt::Display for Network {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    for (name, log) in self.logs() {
+      let values: Vec<String> =
+        log.iter().map(|value| value.to_string()).collect();
+      writeln!(formatter, "{}: {}", name, values.join(","))?;
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::iter;
+
+  use super::{Network, NetworkState, Route};
+  use crate::util::intcode::{assemble, Program};
+
+  #[test]
+  fn feedback_loop() {
+    let stack = vec![
+      3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27,
+      1001, 28, -1, 28, 1005, 28, 6, 99, 0, 0, 5,
+    ];
+    let mut network = Network::new();
+
+    for (index, phase) in [9, 8, 7, 6, 5].iter().enumerate() {
+      let program = Program::new(stack.clone(), vec![*phase]);
+      let name = ((b'A' + index as u8) as char).to_string();
+
+      network.add_program(&name, program);
+      network.connect(index, Route::Wire((index + 1) % 5));
+    }
+
+    network.send(0, &[0]);
+
+    assert_eq!(network.run(), Ok(NetworkState::Halted));
+    assert_eq!(network.log(4).last(), Some(&139629729));
+  }
+
+  #[test]
+  fn routes() {
+    // Emits three packets, to `sum` (1), to `nat` (255) and to `pairs` (2)
+    let packets = vec![1, 10, 20, 255, 5, 6, 2, 7, 8];
+    let source = packets.iter().flat_map(|value| vec![104, *value]);
+    let source = source.chain(iter::once(99)).collect();
+    let adder = assemble(
+      "
+      loop: Input [x]
+            Input [y]
+            Add [x], [y], [x]
+            Output [x]
+            JumpIf #1, #loop
+      x:    .data 0
+      y:    .data 0
+      ",
+    )
+    .unwrap();
+
+    let mut network = Network::new();
+    let source = network.add_program("source", Program::new(source, vec![]));
+    let sum = network.add_program("sum", Program::new(adder.clone(), vec![]));
+    let pairs = network.add_program("pairs", Program::new(adder, vec![]));
+    let hub = network.add_hub("hub");
+    let nat = network.add_nat("nat", 2);
+
+    network.set_address(nat, 255);
+    network.connect(source, Route::Packets(3));
+    network.connect(source, Route::Slot { size: 3, index: 0, to: hub });
+    network.connect(sum, Route::Wire(hub));
+    network.connect(pairs, Route::Wire(hub));
+    network.connect(nat, Route::Wire(sum));
+
+    assert_eq!(network.run(), Ok(NetworkState::Idle));
+    assert_eq!(network.log(hub), &[1, 255, 2, 30, 15]);
+
+    assert!(network.wake());
+    assert_eq!(network.run(), Ok(NetworkState::Idle));
+    assert_eq!(
+      network.to_string(),
+      "source: 1,10,20,255,5,6,2,7,8\nsum: 30,11\npairs: 15\n\
+       hub: 1,255,2,30,15,11\nnat: 5,6\n"
+    );
+  }
+
+  #[test]
+  #[should_panic(expected = "slots of a frame cannot be empty")]
+  fn empty_slots() {
+    let mut network = Network::new();
+    let hub = network.add_hub("hub");
+
+    network.connect(hub, Route::Slot { size: 0, index: 0, to: hub });
+  }
+
+  #[test]
+  #[should_panic(expected = "slot 3 is outside of frames of 3 values")]
+  fn slot_outside_of_frames() {
+    let mut network = Network::new();
+    let hub = network.add_hub("hub");
+
+    network.connect(hub, Route::Slot { size: 3, index: 3, to: hub });
+  }
+
+  #[test]
+  fn hub_cycles() {
+    let mut network = Network::new();
+    let program =
+      network.add_program("echo", Program::new(vec![3, 0, 4, 0, 99], vec![]));
+    let first = network.add_hub("first");
+    let second = network.add_hub("second");
+
+    // Every value goes once through each hub, before reaching the program
+    network.connect(first, Route::Wire(first));
+    network.connect(first, Route::Wire(second));
+    network.connect(second, Route::Wire(first));
+    network.connect(second, Route::Wire(program));
+    network.send(second, &[42]);
+
+    assert_eq!(network.run(), Ok(NetworkState::Halted));
+    assert_eq!(network.log(first), &[42]);
+    assert_eq!(network.log(second), &[42]);
+    assert_eq!(network.log(program), &[42]);
+  }
+}
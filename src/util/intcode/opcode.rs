@@ -1,26 +1,130 @@
 macro_rules! opcodes {
-  ( $( $name:ident << $code:expr ),* ) => {
-    use super::mode::Mode;
+  ( $( $name:ident($arity:expr) << $code:expr ),* ) => {
+    use std::convert::{TryFrom, TryInto};
+
+    use super::{mode::Mode, Reason};
 
     #[derive(Debug, Copy, Clone)]
-    enum Opcode {
+    pub(super) enum Opcode {
       $(
-        $name(Mode, Mode, Mode)
-      ),*
+        $name(Mode, Mode, Mode),
+      )*
+      // Opcode registered by the caller, see `Extensions`
+      Extended(Custom, Mode, Mode, Mode),
+    }
+
+    impl Opcode {
+      // Every built-in opcode, by name, code and number of parameters
+      pub(super) const TABLE: &[(&'static str, i128, usize)] = &[
+        $(
+          (stringify!($name), $code, $arity),
+        )*
+      ];
+
+      // Number of parameters following the opcode in memory
+      pub(super) fn arity(self) -> usize {
+        match self {
+          $(
+            Opcode::$name(..) => $arity,
+          )*
+          Opcode::Extended(custom, ..) => custom.arity,
+        }
+      }
+
+      pub(super) fn name(self) -> &'static str {
+        match self {
+          $(
+            Opcode::$name(..) => stringify!($name),
+          )*
+          Opcode::Extended(custom, ..) => custom.name,
+        }
+      }
+
+      pub(super) fn modes(self) -> [Mode; 3] {
+        match self {
+          $(
+            Opcode::$name(m1, m2, m3) => [m1, m2, m3],
+          )*
+          Opcode::Extended(_, m1, m2, m3) => [m1, m2, m3],
+        }
+      }
+
+      pub(super) fn from_name(name: &str, modes: [Mode; 3]) -> Option<Opcode> {
+        let [m1, m2, m3] = modes;
+        match name {
+          $(
+            stringify!($name) => Some(Opcode::$name(m1, m2, m3)),
+          )*
+          _ => None,
+        }
+      }
+
+      // Memory representation of the opcode, including its parameter modes
+      pub(super) fn encode(self) -> i128 {
+        let [m1, m2, m3] = self.modes();
+        let code = match self {
+          $(
+            Opcode::$name(..) => $code,
+          )*
+          Opcode::Extended(custom, ..) => custom.code,
+        };
+
+        code + m1.value() * 100 + m2.value() * 1000 + m3.value() * 10000
+      }
     }
 
-    impl From<i128> for Opcode {
-      fn from(code: i128) -> Self {
+    impl TryFrom<i128> for Opcode {
+      type Error = Reason;
+
+      fn try_from(code: i128) -> Result<Self, Reason> {
         let modes = (code / 100 % 10, code / 1000 % 10, code / 10000 % 10);
         match code % 100 {
           $(
-            $code => Opcode::$name(modes.0.into(), modes.1.into(), modes.2.into()),
+            $code => Ok(Opcode::$name(
+              modes.0.try_into()?,
+              modes.1.try_into()?,
+              modes.2.try_into()?,
+            )),
           )*
-          _ => panic!("invalid opcode"),
+          _ => Err(Reason::InvalidOpcode),
         }
       }
     }
-
-    use Opcode::*;
   };
 }
+
+// Operation codes, their parameter count and their method are generated by a macro
+opcodes![
+  Add(3) << 1,
+  Mul(3) << 2,
+  Input(1) << 3,
+  Output(1) << 4,
+  JumpIf(2) << 5,
+  JumpUnless(2) << 6,
+  IfLess(3) << 7,
+  IfEquals(3) << 8,
+  SetBase(1) << 9,
+  Halt(0) << 99
+];
+
+// Description of an opcode registered by the caller
+#[derive(Debug, Copy, Clone)]
+pub(super) struct Custom {
+  pub name: &'static str,
+  pub code: i128,
+  pub arity: usize,
+  pub target: Option<usize>,
+}
+
+impl Opcode {
+  // Index of the parameter holding the address written to by the instruction, if any
+  pub(super) fn target(self) -> Option<usize> {
+    match self {
+      Opcode::Add(..) | Opcode::Mul(..) => Some(2),
+      Opcode::IfLess(..) | Opcode::IfEquals(..) => Some(2),
+      Opcode::Input(..) => Some(0),
+      Opcode::Extended(custom, ..) => custom.target,
+      _ => None,
+    }
+  }
+}
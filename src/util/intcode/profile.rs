@@ -0,0 +1,181 @@
+use std::{
+  cmp::Reverse,
+  collections::HashMap,
+  fmt,
+  io::{self, Write},
+};
+
+use super::{Event, Tracer};
+
+// Number of entries listed in every section of the report
+const TOP: usize = 10;
+
+// Tracer counting where a program spends its time
+//  * `addresses` counts executions of every instruction, by address
+//  * `opcodes` counts executions by mnemonic
+//  * `loops` counts backward transfers of control, keyed by the target and the address they were made from
+//  * `stacks` counts executions by pseudo call stack
+//
+// Intcode has no notion of functions, but compiled programs move the relative base forward when entering one and
+// back when leaving it: every increase of the relative base opens a frame named after the instruction that did it,
+// and every decrease closes the frames opened above the new base.
+#[derive(Debug, Default, Clone)]
+pub struct Profiler {
+  pub total: u64,
+  pub addresses: HashMap<usize, (&'static str, u64)>,
+  pub opcodes: HashMap<&'static str, u64>,
+  pub loops: HashMap<(usize, usize), u64>,
+  pub stacks: HashMap<Vec<usize>, u64>,
+  // Open frames, by the address they were opened at and the relative base they set
+  path: Vec<usize>,
+  frames: Vec<usize>,
+  base: usize,
+  previous: Option<usize>,
+}
+
+impl Profiler {
+  pub fn new() -> Profiler {
+    Profiler::default()
+  }
+
+  // Write the pseudo call stacks in the folded format used by flamegraph tools, one `main;frame;frame count` per line
+  pub fn write_folded<O: Write>(&self, mut writer: O) -> io::Result<()> {
+    let mut stacks: Vec<(&Vec<usize>, &u64)> = self.stacks.iter().collect();
+    stacks.sort_unstable();
+
+    for (path, count) in stacks {
+      write!(writer, "main")?;
+      for frame in path {
+        write!(writer, ";{:04}", frame)?;
+      }
+      writeln!(writer, " {}", count)?;
+    }
+
+    Ok(())
+  }
+
+  fn share(&self, count: u64) -> f64 {
+    count as f64 * 100.0 / self.total.max(1) as f64
+  }
+}
+
+impl<W> Tracer<W> for Profiler {
+  fn trace(&mut self, event: &Event<W>) -> io::Result<()> {
+    self.total += 1;
+    self.addresses.entry(event.address).or_insert((event.mnemonic, 0)).1 += 1;
+    *self.opcodes.entry(event.mnemonic).or_default() += 1;
+
+    if let Some(previous) = self.previous {
+      if event.address <= previous {
+        *self.loops.entry((event.address, previous)).or_default() += 1;
+      }
+    }
+    self.previous = Some(event.address);
+
+    // Looked up by slice, so the path is only allocated for new stacks
+    match self.stacks.get_mut(self.path.as_slice()) {
+      Some(count) => *count += 1,
+      None => {
+        self.stacks.insert(self.path.clone(), 1);
+      }
+    }
+
+    if let Some(base) = event.base {
+      if base > self.base {
+        self.frames.push(base);
+        self.path.push(event.address);
+      } else {
+        while self.frames.last().is_some_and(|opened| *opened > base) {
+          self.frames.pop();
+          self.path.pop();
+        }
+      }
+      self.base = base;
+    }
+
+    Ok(())
+  }
+}
+
+// Report of the busiest opcodes, addresses and loops, by decreasing execution count
+impl fmt::Display for Profiler {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    writeln!(formatter, "{} instructions executed", self.total)?;
+
+    let mut opcodes: Vec<(&&str, &u64)> = self.opcodes.iter().collect();
+    opcodes.sort_unstable_by_key(|(name, count)| (Reverse(**count), **name));
+
+    writeln!(formatter, "\nOpcodes:")?;
+    for (name, count) in opcodes {
+      let share = self.share(*count);
+      writeln!(formatter, "  {:<16} {:>12} {:>6.1}%", name, count, share)?;
+    }
+
+    let mut addresses: Vec<_> = self.addresses.iter().collect();
+    addresses.sort_unstable_by_key(|(address, (_, count))| {
+      (Reverse(*count), **address)
+    });
+
+    writeln!(formatter, "\nHot addresses:")?;
+    for (address, (name, count)) in addresses.into_iter().take(TOP) {
+      let share = self.share(*count);
+      writeln!(
+        formatter,
+        "  {:04}  {:<10} {:>12} {:>6.1}%",
+        address, name, count, share
+      )?;
+    }
+
+    let mut loops: Vec<_> = self.loops.iter().collect();
+    loops.sort_unstable_by_key(|(range, count)| (Reverse(**count), **range));
+
+    writeln!(formatter, "\nHot loops:")?;
+    for ((start, end), count) in loops.into_iter().take(TOP) {
+      writeln!(
+        formatter,
+        "  {:04}-{:04} {:>12} iterations",
+        start, end, count
+      )?;
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Profiler;
+  use crate::util::intcode::{assemble, Program, RunState};
+
+  #[test]
+  fn profile() {
+    let source = "
+      start:  Add [count], #-1, [count]
+              SetBase #10                 ; Enters a frame
+              SetBase #-10                ; Leaves it
+              JumpIf [count], #start
+              Halt
+      count:  .data 3
+    ";
+
+    let program = Program::new(assemble(source).unwrap(), vec![]);
+    let mut program = program.with_tracer(Profiler::new());
+
+    assert_eq!(program.run_until_blocked(), Ok(RunState::Halted));
+
+    let profiler = program.tracer;
+    assert_eq!(profiler.total, 13);
+    assert_eq!(profiler.opcodes["SetBase"], 6);
+    assert_eq!(profiler.addresses[&8], ("JumpIf", 3));
+    assert_eq!(profiler.loops[&(0, 8)], 2);
+
+    let mut folded = vec![];
+    profiler.write_folded(&mut folded).unwrap();
+    assert_eq!(String::from_utf8(folded).unwrap(), "main 10\nmain;0004 3\n");
+
+    let report = profiler.to_string();
+    assert!(report.starts_with("13 instructions executed\n"));
+    assert!(report.contains("  SetBase                     6   46.2%\n"));
+    assert!(report.contains("  0000-0008            2 iterations\n"));
+  }
+}
@@ -1,207 +1,646 @@
-// Operation codes and their method are generated by a macro
-opcodes![
-  Add << 1,
-  Mul << 2,
-  Input << 3,
-  Output << 4,
-  JumpIf << 5,
-  JumpUnless << 6,
-  IfLess << 7,
-  IfEquals << 8,
-  SetBase << 9,
-  Halt << 99
-];
-
-#[derive(Debug)]
-enum State {
-  // New yet to be readinstruction
-  Initial,
-  // Opcode and operation modes were read
-  Op(Opcode),
-  // Opcode, operation modes and at least one parameter were read
-  Params { op: Opcode, params: Vec<i128> },
+use super::{
+  compiler::{self, compile, Cache, Op, Operand, Step, Target},
+  instruction::Instruction,
+  limits::Budget,
+  mode::Mode,
+  opcode::Opcode::*,
+  Effect, Engine, Event, Extensions, IntcodeError, IntcodeIo, Limits, Memory,
+  Paged, Queue, Reason, Snapshot, Tracer, Word,
+};
+
+// Reason why a program stopped executing
+//  * ExitStatus::Output means the program emitted a value and can be resumed
+//  * ExitStatus::Halted means the Halt instruction was reached
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ExitStatus<W = i128> {
+  Output(W),
+  Halted,
+}
+
+// State of a program after it was given a chance to run
+//  * RunState::Running means the last instruction did not require any interaction
+//  * RunState::AwaitingInput means the program is blocked until an input is provided
+//  * RunState::Output means the program emitted a value and can be resumed
+//  * RunState::Halted means the Halt instruction was reached
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum RunState<W = i128> {
+  Running,
+  AwaitingInput,
+  Output(W),
+  Halted,
 }
 
-#[derive(Debug)]
-pub struct Program {
-  state: State,
+// A program is generic over the type of its words (`i128` by default), every arithmetic operation being checked
+#[derive(Debug, Clone)]
+pub struct Program<IO = Queue, M = Paged, T = (), W = i128> {
   pointer: usize,
   base: usize,
-  heap: Vec<i128>,
-  pub stack: Vec<i128>,
-  pub inputs: Vec<i128>,
-  pub retval: Option<i128>,
+  heap: M,
+  pub stack: Vec<W>,
+  pub io: IO,
+  pub retval: Option<W>,
   pub paused: bool,
   pub halted: bool,
+  pub limits: Limits,
+  pub tracer: T,
+  pub extensions: Extensions<W>,
+  pub engine: Engine,
+  cache: Cache<W>,
+  // Number of instructions executed so far, checked against the limits
+  executed: u64,
+  budget: Budget,
 }
 
 impl Program {
   pub fn new(stack: Vec<i128>, inputs: Vec<i128>) -> Program {
+    Program::with_io(stack, Queue::new(inputs))
+  }
+}
+
+impl Program {
+  pub fn restore(snapshot: &Snapshot) -> Program {
+    Program::restore_with_memory(snapshot, Paged::default())
+  }
+}
+
+impl<M> Program<Queue, M>
+where
+  M: Memory,
+{
+  // Rebuild a program from a snapshot, its heap being loaded into `heap`
+  // Snapshots do not hold any configuration: the program has no limits, no extensions, and uses the default engine
+  // Use `restore_into` to keep the configuration of an existing program instead
+  pub fn restore_with_memory(
+    snapshot: &Snapshot, heap: M,
+  ) -> Program<Queue, M> {
+    let mut program = Program::with_memory(vec![], Queue::default(), heap);
+    program.load(snapshot);
+    program
+  }
+}
+
+impl<M, T> Program<Queue, M, T>
+where
+  M: Memory + Default,
+{
+  // Replace the state of the machine by the one of a snapshot
+  // Its limits, extensions, engine and tracer are kept, the time limit starting over
+  pub fn restore_into(&mut self, snapshot: &Snapshot) {
+    self.heap = M::default();
+    self.load(snapshot);
+  }
+}
+
+impl<M, T> Program<Queue, M, T>
+where
+  M: Memory,
+{
+  // Load the state of a snapshot into a program with an empty heap
+  fn load(&mut self, snapshot: &Snapshot) {
+    for (address, value) in &snapshot.heap {
+      self.heap.set(*address, *value);
+    }
+
+    self.pointer = snapshot.pointer;
+    self.base = snapshot.base;
+    self.stack = snapshot.stack.clone();
+    self.io = Queue {
+      inputs: snapshot.inputs.iter().copied().collect(),
+      outputs: snapshot.outputs.iter().copied().collect(),
+    };
+    self.retval = snapshot.retval;
+    self.paused = snapshot.paused;
+    self.halted = snapshot.halted;
+    self.cache = Cache::new();
+    self.executed = snapshot.executed;
+    self.budget = Budget::default();
+  }
+
+  // Capture the whole state of the program, including its pending inputs and outputs
+  pub fn snapshot(&self) -> Snapshot {
+    Snapshot {
+      pointer: self.pointer,
+      base: self.base,
+      stack: self.stack.clone(),
+      heap: self.heap.cells(),
+      inputs: self.io.inputs.iter().copied().collect(),
+      outputs: self.io.outputs.iter().copied().collect(),
+      retval: self.retval,
+      paused: self.paused,
+      halted: self.halted,
+      executed: self.executed,
+    }
+  }
+}
+
+impl<IO> Program<IO>
+where
+  IO: IntcodeIo,
+{
+  pub fn with_io(stack: Vec<i128>, io: IO) -> Program<IO> {
+    Program::with_memory(stack, io, Paged::default())
+  }
+}
+
+impl<IO, M, W> Program<IO, M, (), W>
+where
+  IO: IntcodeIo<W>,
+  M: Memory<W>,
+  W: Word,
+{
+  pub fn with_memory(stack: Vec<W>, io: IO, heap: M) -> Program<IO, M, (), W> {
     Program {
-      state: State::Initial,
       pointer: 0,
       base: 0,
-      heap: vec![],
+      heap,
       stack,
-      inputs,
+      io,
       retval: None,
       paused: true,
       halted: false,
+      limits: Limits::default(),
+      tracer: (),
+      extensions: Extensions::new(),
+      engine: Engine::default(),
+      cache: Cache::new(),
+      executed: 0,
+      budget: Budget::default(),
     }
   }
+}
 
-  // Read current instruction
-  fn next(&mut self, param: i128) {
-    self.state = match self.state {
-      // On clean state, the only thing that can be read is an opcode
-      State::Initial => State::Op(param.into()),
-
-      // When we get an opcode, the next thing to be read is a param (if applicable)
-      State::Op(ref op) => State::Params { op: *op, params: vec![param] },
+impl<IO, M, T, W> Program<IO, M, T, W>
+where
+  IO: IntcodeIo<W>,
+  M: Memory<W>,
+  T: Tracer<W>,
+  W: Word,
+{
+  // Attach a tracer, notified of every instruction executed from now on
+  pub fn with_tracer<U: Tracer<W>>(self, tracer: U) -> Program<IO, M, U, W> {
+    Program {
+      pointer: self.pointer,
+      base: self.base,
+      heap: self.heap,
+      stack: self.stack,
+      io: self.io,
+      retval: self.retval,
+      paused: self.paused,
+      halted: self.halted,
+      limits: self.limits,
+      tracer,
+      extensions: self.extensions,
+      engine: self.engine,
+      cache: self.cache,
+      executed: self.executed,
+      budget: self.budget,
+    }
+  }
 
-      // This state is a recursive one, adding new parameters until we get the proper count for the opcode
-      State::Params { op, ref params } => {
-        let mut params = params.to_vec();
-        params.push(param);
-        State::Params { op, params }
-      }
-    };
+  // Build the error describing why the current instruction cannot be executed
+  fn fault(&self, reason: Reason) -> IntcodeError<W> {
+    IntcodeError {
+      pointer: self.pointer,
+      opcode: self.get(self.pointer),
+      reason,
+    }
   }
 
-  // Reset state machine when current instruction is finished
-  fn reset(&mut self) {
-    self.state = State::Initial;
+  // Read the whole instruction under the instruction pointer
+  fn decode(&self) -> Result<Instruction<W>, IntcodeError<W>> {
+    let pointer = self.pointer;
+
+    let read = |address| self.get(address);
+
+    match Instruction::decode(pointer, read, &self.extensions) {
+      Ok(instruction) => Ok(instruction),
+      Err(reason) => Err(self.fault(reason)),
+    }
   }
 
   // Loop over all instructions in memory until an output is given or the Halt instruction is found
-  pub fn execute(&mut self) {
+  pub fn execute(&mut self) -> Result<ExitStatus<W>, IntcodeError<W>> {
+    match self.run_until_event()? {
+      RunState::Output(retval) => Ok(ExitStatus::Output(retval)),
+      RunState::Halted => Ok(ExitStatus::Halted),
+      // Inputs are expected to be provided upfront in this mode
+      _ => Err(self.fault(Reason::MissingInput)),
+    }
+  }
+
+  pub fn execute_for_output(&mut self) -> Result<Option<W>, IntcodeError<W>> {
+    self.execute()?;
+    Ok(self.retval.clone())
+  }
+
+  // Loop over all instructions in memory until the program requires an interaction
+  // When RunState::AwaitingInput is returned, the program can be resumed once its IO has an input available
+  pub fn run_until_event(&mut self) -> Result<RunState<W>, IntcodeError<W>> {
+    let state = self.run()?;
+    self.hand_back(&state);
+    Ok(state)
+  }
+
+  // Loop over all instructions in memory until the program needs an input or halts
+  // Outputs are not interrupting the program, they can be retrieved from its IO
+  pub fn run_until_blocked(&mut self) -> Result<RunState<W>, IntcodeError<W>> {
+    loop {
+      match self.run()? {
+        RunState::Output(_) => continue,
+        state => return Ok(state),
+      }
+    }
+  }
+
+  fn run(&mut self) -> Result<RunState<W>, IntcodeError<W>> {
     self.paused = false;
-    while !self.halted && !self.paused {
-      self.tick()
+    loop {
+      // Traced programs need every instruction to go through the tracer
+      if !T::ENABLED && self.engine == Engine::Compiler && !self.halted {
+        self.run_compiled()?;
+      }
+
+      match self.advance()? {
+        RunState::Running => continue,
+        state => return Ok(state),
+      }
     }
   }
 
-  pub fn execute_for_output(&mut self) -> Option<i128> {
-    self.execute();
-    self.retval
+  // Execute a single whole instruction, unless the program ran out of its limits
+  pub fn step(&mut self) -> Result<RunState<W>, IntcodeError<W>> {
+    let state = self.advance()?;
+    self.hand_back(&state);
+    Ok(state)
   }
 
-  fn tick(&mut self) {
-    // Get the instruction under the instruction pointer
-    let instruction = if self.stack.len() > self.pointer {
-      self.stack[self.pointer]
+  // An output returned to the caller is not kept by the IO as well
+  fn hand_back(&mut self, state: &RunState<W>) {
+    if let RunState::Output(_) = state {
+      self.io.handed_back();
+    }
+  }
+
+  fn advance(&mut self) -> Result<RunState<W>, IntcodeError<W>> {
+    // Nothing is executed anymore once halted, the pointer possibly being on an extension that halted the program
+    if self.halted {
+      return Ok(RunState::Halted);
+    }
+
+    self.check_limits()?;
+
+    let instruction = self.decode()?;
+    let state = if T::ENABLED {
+      self.exec_traced(&instruction)?
     } else {
-      0
+      self.exec(&instruction)?
     };
 
-    match self.state {
-      State::Initial => self.next(instruction),
-      State::Op(Halt(..)) => {
-        self.halted = true;
-        return;
+    // An instruction waiting for its input was not executed yet
+    if state != RunState::AwaitingInput {
+      self.executed += 1;
+    }
+
+    Ok(state)
+  }
+
+  fn check_limits(&mut self) -> Result<(), IntcodeError<W>> {
+    match self.budget.exceeded(&self.limits, self.executed) {
+      Some(limit) => Err(self.fault(Reason::LimitExceeded(limit))),
+      None => Ok(()),
+    }
+  }
+
+  // Execute compiled blocks from the instruction pointer, until reaching an instruction left to the interpreter
+  fn run_compiled(&mut self) -> Result<(), IntcodeError<W>> {
+    loop {
+      self.check_limits()?;
+
+      let block = match self.cache.get(self.pointer) {
+        Some(block) => block,
+        None if self.cache.hot(self.pointer, self.stack.len()) => {
+          let block = compile(self.pointer, &self.stack, &self.extensions);
+          // Empty blocks are not cached, since nothing would invalidate them
+          if block.steps.is_empty() {
+            return Ok(());
+          }
+          self.cache.insert(block)
+        }
+        None => return Ok(()),
+      };
+
+      // The last instructions before the limit are executed one by one
+      let size = block.steps.len() as u64;
+      if self.limits.instructions.is_some_and(|n| self.executed + size > n) {
+        return Ok(());
       }
-      State::Op { .. } => self.next(instruction),
-
-      // If we have an opcode, we read its parameters until the branch for the appropriate opcode is satisfied with the number of parameters
-      //  * (Op1(m1, m2, m3), &[v1, v2, r]) means that, for the Op1 opcode, we are interested in the three modes of operation and stop reading when we get three parameters
-      //  * (Op2(m1, _, _), &[r]) means that, for the Op2 opcode, we are interested in the first mode of operation and stop reading when we have one parameter
-      State::Params { op: code, ref params } => match (code, params.as_slice())
-      {
-        // This should never happen (it is handled at the Op stage)
-        (Halt(..), _) => unimplemented!(),
-
-        // Adds the values of `v1` and `v2` into `r`
-        (Add(m1, m2, m3), &[v1, v2, r]) => {
-          let (v1, v2, r) = (
-            self.deref(m1, v1),
-            self.deref(m2, v2),
-            self.address(m3, r as usize),
-          );
-          self.set(r, v1 + v2);
-          self.reset();
+
+      for step in &block.steps {
+        let (next, invalidated) = match self.exec_compiled(step) {
+          Some(result) => result,
+          None => return Ok(()),
+        };
+
+        self.executed += 1;
+        self.pointer = next;
+
+        // The rest of the block might not exist anymore
+        if invalidated {
+          break;
         }
+      }
+    }
+  }
+
+  // Execute a compiled instruction, returning the address of the next one and whether it wrote into compiled code
+  // Nothing is done when the instruction would fail, for the interpreter to report the error
+  fn exec_compiled(&mut self, step: &Step<W>) -> Option<(usize, bool)> {
+    let mut next = step.next;
+    let mut invalidated = false;
 
-        // Multiplies the values of `v1` and `v2` into `r`
-        (Mul(m1, m2, m3), &[v1, v2, r]) => {
-          let (v1, v2, r) = (
-            self.deref(m1, v1),
-            self.deref(m2, v2),
-            self.address(m3, r as usize),
-          );
-          self.set(r, v1 * v2);
-          self.reset();
+    match &step.op {
+      Op::Add(o1, o2, target) => {
+        let (v1, v2) = (self.operand(o1)?, self.operand(o2)?);
+        let r = self.target(target)?;
+        invalidated = self.set(r, v1.checked_add(&v2)?);
+      }
+      Op::Mul(o1, o2, target) => {
+        let (v1, v2) = (self.operand(o1)?, self.operand(o2)?);
+        let r = self.target(target)?;
+        invalidated = self.set(r, v1.checked_mul(&v2)?);
+      }
+      Op::Less(o1, o2, target) => {
+        let v = self.operand(o1)? < self.operand(o2)?;
+        let r = self.target(target)?;
+        invalidated = self.set(r, if v { W::one() } else { W::zero() });
+      }
+      Op::Equals(o1, o2, target) => {
+        let v = self.operand(o1)? == self.operand(o2)?;
+        let r = self.target(target)?;
+        invalidated = self.set(r, if v { W::one() } else { W::zero() });
+      }
+      Op::SetBase(o1) => self.base = self.relative(&self.operand(o1)?)?,
+      Op::JumpIf(o1, o2) => {
+        if !self.operand(o1)?.is_zero() {
+          next = compiler::address(&self.operand(o2)?)?;
         }
+      }
+      Op::JumpUnless(o1, o2) => {
+        if self.operand(o1)?.is_zero() {
+          next = compiler::address(&self.operand(o2)?)?;
+        }
+      }
+    }
+
+    Some((next, invalidated))
+  }
+
+  fn operand(&self, operand: &Operand<W>) -> Option<W> {
+    match operand {
+      Operand::Value(value) => Some(value.clone()),
+      Operand::Position(address) => Some(self.get(*address)),
+      Operand::Relative(offset) => Some(self.get(self.relative(offset)?)),
+    }
+  }
+
+  fn target(&self, target: &Target<W>) -> Option<usize> {
+    match target {
+      Target::Position(address) => Some(*address),
+      Target::Relative(offset) => self.relative(offset),
+    }
+  }
+
+  fn relative(&self, offset: &W) -> Option<usize> {
+    compiler::address(&W::from_usize(self.base)?.checked_add(offset)?)
+  }
 
-        // Requests an input value from the input queue. Once consumed, the input is removed from the queue
-        (Input(m1, ..), &[r]) => {
-          // A program should always be provided with enough inputs to function properly
-          if self.inputs.is_empty() {
-            panic!("not enough inputs");
+  // Execute an instruction, and report everything it did to the tracer
+  fn exec_traced(
+    &mut self, instruction: &Instruction<W>,
+  ) -> Result<RunState<W>, IntcodeError<W>> {
+    let (address, base) = (self.pointer, self.base);
+    let opcode = self.get(address);
+
+    // Operands the instruction does not use (such as the target of a jump that is not taken) might not resolve,
+    // they are traced as their raw parameter and failures are left to the instruction itself
+    let mut params = instruction.params.clone();
+    for (index, param) in
+      params.iter_mut().take(instruction.op.arity()).enumerate()
+    {
+      if let Ok(value) = self.resolve_at(instruction, index) {
+        *param = value;
+      }
+    }
+
+    let state = self.exec(instruction)?;
+
+    // An instruction waiting for its input was not executed yet
+    if state == RunState::AwaitingInput {
+      return Ok(state);
+    }
+
+    let write = instruction.op.target().map(|index| {
+      // Already checked when the operands were resolved
+      let target = params[index].to_usize().unwrap_or_default();
+      (target, self.get(target))
+    });
+
+    let event = Event {
+      address,
+      opcode,
+      mnemonic: instruction.op.name(),
+      arity: instruction.op.arity(),
+      params,
+      write,
+      base: Some(self.base).filter(|current| *current != base),
+    };
+
+    if let Err(err) = self.tracer.trace(&event) {
+      let reason = Reason::Io(err.kind());
+      return Err(IntcodeError {
+        pointer: address,
+        opcode: event.opcode,
+        reason,
+      });
+    }
+
+    Ok(state)
+  }
+
+  fn exec(
+    &mut self, instruction: &Instruction<W>,
+  ) -> Result<RunState<W>, IntcodeError<W>> {
+    let [p1, p2, p3] = &instruction.params;
+
+    // Address of the next instruction, unless the current one jumps somewhere else
+    // It only faults when used, so an instruction ending the address space can still halt or jump
+    let mut next = instruction.next(self.pointer);
+
+    match instruction.op {
+      // The instruction pointer stays on the Halt instruction, so resuming the program halts it again
+      Halt(..) => {
+        self.halted = true;
+        return Ok(RunState::Halted);
+      }
+
+      // Adds the values of `v1` and `v2` into `r`
+      Add(m1, m2, m3) => {
+        let (v1, v2, r) =
+          (self.deref(m1, p1)?, self.deref(m2, p2)?, self.address(m3, p3)?);
+        match v1.checked_add(&v2) {
+          Some(v) => {
+            self.set(r, v);
           }
-          let r = self.address(m1, r as usize);
-          let v = self.inputs.remove(0);
-          self.set(r, v);
-          self.reset();
+          None => return Err(self.fault(Reason::Overflow)),
         }
+      }
 
-        // Outputs a result value. The program is paused (but keeps its state), until resumed
-        (Output(m, ..), &[v]) => {
-          self.paused = true;
-          self.retval = Some(self.deref(m, v));
-          self.reset();
-          return;
+      // Multiplies the values of `v1` and `v2` into `r`
+      Mul(m1, m2, m3) => {
+        let (v1, v2, r) =
+          (self.deref(m1, p1)?, self.deref(m2, p2)?, self.address(m3, p3)?);
+        match v1.checked_mul(&v2) {
+          Some(v) => {
+            self.set(r, v);
+          }
+          None => return Err(self.fault(Reason::Overflow)),
         }
+      }
 
-        // If `cond` is different than zero, change the instruction pointer to `address`
-        (JumpIf(m1, m2, _), &[cond, address]) => {
-          if self.deref(m1, cond) != 0 {
-            self.pointer = self.deref(m2, address) as usize;
-          }
-          self.reset();
+      // Requests an input value from the program IO
+      Input(m1, ..) => {
+        let r = self.address(m1, p1)?;
+        let v = match self.io.read() {
+          Ok(Some(v)) => v,
+          // Without any input, the program stays on this instruction until one is provided
+          Ok(None) => return Ok(RunState::AwaitingInput),
+          Err(err) => return Err(self.fault(Reason::Io(err.kind()))),
+        };
+        self.set(r, v);
+      }
+
+      // Outputs a result value. The program is paused (but keeps its state), until resumed
+      Output(m1, ..) => {
+        let retval = self.deref(m1, p1)?;
+        let next = next.ok_or_else(|| self.fault(Reason::Overflow))?;
+        return self.output(retval, next);
+      }
+
+      // If `cond` is different than zero, change the instruction pointer to `address`
+      JumpIf(m1, m2, _) => {
+        if !self.deref(m1, p1)?.is_zero() {
+          next = Some(self.to_address(&self.deref(m2, p2)?)?);
         }
-        // If `cond` equals zero, change the instruction pointer to `address`
-        (JumpUnless(m1, m2, _), &[cond, address]) => {
-          if self.deref(m1, cond) == 0 {
-            self.pointer = self.deref(m2, address) as usize;
-          }
-          self.reset();
+      }
+
+      // If `cond` equals zero, change the instruction pointer to `address`
+      JumpUnless(m1, m2, _) => {
+        if self.deref(m1, p1)?.is_zero() {
+          next = Some(self.to_address(&self.deref(m2, p2)?)?);
         }
-        // If `v1` is less than `v2`, write `0` to `address`, else, write `1`
-        (IfLess(m1, m2, m3), &[v1, v2, r]) => {
-          let r = self.address(m3, r as usize);
-          if self.deref(m1, v1) < self.deref(m2, v2) {
-            self.set(r, 1);
-          } else {
-            self.set(r, 0);
+      }
+
+      // If `v1` is less than `v2`, write `1` to `address`, else, write `0`
+      IfLess(m1, m2, m3) => {
+        let r = self.address(m3, p3)?;
+        let v = self.deref(m1, p1)? < self.deref(m2, p2)?;
+        self.set(r, if v { W::one() } else { W::zero() });
+      }
+
+      // If `v1` equals `v2`, write `1` to `address`, else, write `0`
+      IfEquals(m1, m2, m3) => {
+        let r = self.address(m3, p3)?;
+        let v = self.deref(m1, p1)? == self.deref(m2, p2)?;
+        self.set(r, if v { W::one() } else { W::zero() });
+      }
+
+      // Set the offset base to `v1`
+      SetBase(m1, ..) => {
+        self.base = self.offset(&self.deref(m1, p1)?)?;
+      }
+
+      // Delegate to the handler registered for the opcode
+      Extended(custom, ..) => {
+        let operands = self.resolve(instruction)?;
+        let handler = match self.extensions.handler(custom.code) {
+          Some(handler) => handler,
+          None => return Err(self.fault(Reason::InvalidOpcode)),
+        };
+
+        match handler(&operands[..custom.arity]) {
+          Ok(Effect::Continue) => (),
+          Ok(Effect::Write(value)) => {
+            let target = match custom.target {
+              Some(target) => target,
+              None => return Err(self.fault(Reason::MissingTarget)),
+            };
+            let mode = instruction.op.modes()[target];
+            let r = self.address(mode, &instruction.params[target])?;
+            self.set(r, value);
           }
-          self.reset();
-        }
-        // If `v1` equals `v2`, write `1` to `address`, else, write `0`
-        (IfEquals(m1, m2, m3), &[v1, v2, r]) => {
-          let r = self.address(m3, r as usize);
-          if self.deref(m1, v1) == self.deref(m2, v2) {
-            self.set(r, 1);
-          } else {
-            self.set(r, 0);
+          Ok(Effect::Jump(address)) => next = Some(self.to_address(&address)?),
+          Ok(Effect::Output(value)) => {
+            let next = next.ok_or_else(|| self.fault(Reason::Overflow))?;
+            return self.output(value, next);
+          }
+          Ok(Effect::Halt(code)) => {
+            if code.is_some() {
+              self.retval = code;
+            }
+            self.halted = true;
+            return Ok(RunState::Halted);
           }
-          self.reset();
+          Err(reason) => return Err(self.fault(reason)),
         }
+      }
+    }
 
-        // Set the offset base to `v1`
-        (SetBase(m1, ..), &[v1]) => {
-          self.base = (self.base as i128 + self.deref(m1, v1)) as usize;
-          self.reset();
-        }
+    self.pointer = next.ok_or_else(|| self.fault(Reason::Overflow))?;
+
+    Ok(RunState::Running)
+  }
+
+  // Emit a value through the program IO, and pause before the instruction at `next`
+  fn output(
+    &mut self, retval: W, next: usize,
+  ) -> Result<RunState<W>, IntcodeError<W>> {
+    if let Err(err) = self.io.write(retval.clone()) {
+      return Err(self.fault(Reason::Io(err.kind())));
+    }
+    self.paused = true;
+    self.retval = Some(retval.clone());
+    self.pointer = next;
 
-        // We are missing some parameters for the current opcode, continue reading instructions
-        _ => self.next(instruction),
-      },
+    Ok(RunState::Output(retval))
+  }
+
+  // Operands of an instruction, as seen by the instruction before it is executed
+  // The written operand is resolved to its address, the others to their values
+  fn resolve(
+    &self, instruction: &Instruction<W>,
+  ) -> Result<[W; 3], IntcodeError<W>> {
+    let mut values = [W::zero(), W::zero(), W::zero()];
+
+    for (index, value) in
+      values.iter_mut().take(instruction.op.arity()).enumerate()
+    {
+      *value = self.resolve_at(instruction, index)?;
     }
 
-    // Advance the instruction pointer, except when we are in a new instruction
-    if let State::Initial = self.state {
+    Ok(values)
+  }
+
+  fn resolve_at(
+    &self, instruction: &Instruction<W>, index: usize,
+  ) -> Result<W, IntcodeError<W>> {
+    let mode = instruction.op.modes()[index];
+    let param = &instruction.params[index];
+
+    if instruction.op.target() == Some(index) {
+      let address = self.address(mode, param)?;
+      W::from_usize(address).ok_or_else(|| self.fault(Reason::Overflow))
     } else {
-      self.pointer += 1;
+      self.deref(mode, param)
     }
   }
 
@@ -209,53 +648,213 @@ impl Program {
   //  * Mode::Position returns the memory at address `value`
   //  * Mode::Immediate returns the value itself
   //  * Mode::Relative returns the memory at address `value`, offset by the current value of the base
-  fn deref(&mut self, mode: Mode, value: i128) -> i128 {
+  fn deref(&self, mode: Mode, value: &W) -> Result<W, IntcodeError<W>> {
     match mode {
-      Mode::Position => self.get(value as usize),
-      Mode::Immediate => value,
-      Mode::Relative => self.get((self.base as i128 + value) as usize),
+      Mode::Position => Ok(self.get(self.to_address(value)?)),
+      Mode::Immediate => Ok(value.clone()),
+      Mode::Relative => Ok(self.get(self.offset(value)?)),
     }
   }
 
   // Return a memory address, possibly offset by the base if in Mode::Relative mode.
-  fn address(&self, mode: Mode, address: usize) -> usize {
+  fn address(&self, mode: Mode, value: &W) -> Result<usize, IntcodeError<W>> {
     match mode {
-      Mode::Position => address,
-      Mode::Relative => (self.base as i128 + address as i128) as usize,
-      _ => panic!("cannot write with mode immediate"),
+      Mode::Position => self.to_address(value),
+      Mode::Relative => self.offset(value),
+      Mode::Immediate => Err(self.fault(Reason::ImmediateWrite)),
     }
   }
 
+  // Convert a word to a memory address, failing on negative values and values too large for the platform
+  fn to_address(&self, value: &W) -> Result<usize, IntcodeError<W>> {
+    if *value < W::zero() {
+      return Err(self.fault(Reason::NegativeAddress));
+    }
+
+    value.to_usize().ok_or_else(|| self.fault(Reason::Overflow))
+  }
+
+  // Return the address `value` words away from the relative base
+  fn offset(&self, value: &W) -> Result<usize, IntcodeError<W>> {
+    let address = W::from_usize(self.base)
+      .and_then(|base| base.checked_add(value))
+      .ok_or_else(|| self.fault(Reason::Overflow))?;
+
+    self.to_address(&address)
+  }
+
+  // Address of the next instruction to be executed
+  pub fn pointer(&self) -> usize {
+    self.pointer
+  }
+
+  // Current value of the relative base
+  pub fn base(&self) -> usize {
+    self.base
+  }
+
+  // Read the memory at the provided address, without executing anything
+  pub fn peek(&self, address: usize) -> W {
+    self.get(address)
+  }
+
+  // Write the memory at the provided address, dropping the compiled code it is part of
+  // Writing to `stack` directly is only safe before the program is run
+  pub fn poke(&mut self, address: usize, value: W) {
+    self.set(address, value);
+  }
+
+  // Number of instructions executed since the program was loaded
+  pub fn executed(&self) -> u64 {
+    self.executed
+  }
+
+  // Number of memory words allocated by the program, including its stack
+  pub fn memory_usage(&self) -> usize {
+    self.stack.len() + self.heap.usage()
+  }
+
+  // Address following the last memory cell holding a value, or the end of the stack
+  pub fn extent(&self) -> usize {
+    self.stack.len().saturating_add(self.heap.extent())
+  }
+
   // Returns the memory at the provided address
   // If the memory index exceeds the main memory size, it means we are looking at an extended memory address, in the heap
-  fn get(&mut self, address: usize) -> i128 {
+  fn get(&self, address: usize) -> W {
     if address >= self.stack.len() {
-      let offset = address as usize - self.stack.len() + 1;
-
-      // The heap starts empty, if we are trying to write to a non-existing chunk of memory, we first need to extend the heap to accomodate for this new value
-      if offset > self.heap.len() {
-        self.heap.resize_with(offset, Default::default);
-      }
-
-      self.heap[offset - 1]
+      self.heap.get(address - self.stack.len())
     } else {
-      self.stack[address]
+      self.stack[address].clone()
     }
   }
 
-  // Writes a value to memory at the provided address
+  // Writes a value to memory at the provided address, telling whether compiled code was overwritten
   // If the memory index exceeds the main memory size, it means we are looking at an extended memory address, in the heap
-  fn set(&mut self, address: usize, value: i128) {
+  fn set(&mut self, address: usize, value: W) -> bool {
     if address >= self.stack.len() {
-      let offset = address as usize - self.stack.len() + 1;
-
-      if offset > self.heap.len() {
-        self.heap.resize_with(offset, Default::default);
-      }
-
-      self.heap[offset - 1] = value;
+      self.heap.set(address - self.stack.len(), value);
+      false
     } else {
       self.stack[address] = value;
+      self.cache.invalidate(address)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use num::BigInt;
+
+  use super::{ExitStatus, Program, RunState};
+  use crate::util::intcode::{
+    IntcodeError, Limit, Limits, Paged, Queue, Reason,
+  };
+
+  #[test]
+  fn errors() {
+    let data = vec![
+      (vec![1, 0, 0, 0, 42], vec![], 4, 42, Reason::InvalidOpcode),
+      (vec![1, 0, 0, 0, 301, 0, 0, 0], vec![], 4, 301, Reason::InvalidMode(3)),
+      (vec![11101, 1, 1, 4, 99], vec![], 0, 11101, Reason::ImmediateWrite),
+      (vec![3, 0, 3, 0, 99], vec![1], 2, 3, Reason::MissingInput),
+      (vec![1, -1, 0, 0, 99], vec![], 0, 1, Reason::NegativeAddress),
+      (vec![109, 1, 204, -2, 99], vec![], 2, 204, Reason::NegativeAddress),
+      (vec![1105, 1, -4, 99], vec![], 0, 1105, Reason::NegativeAddress),
+    ];
+
+    for (stack, inputs, pointer, opcode, reason) in data {
+      let mut program = Program::new(stack, inputs);
+
+      assert_eq!(
+        program.execute(),
+        Err(IntcodeError { pointer, opcode, reason })
+      );
     }
   }
+
+  #[test]
+  fn exit_status() {
+    let mut program = Program::new(vec![104, 42, 99], vec![]);
+
+    assert_eq!(program.execute(), Ok(ExitStatus::Output(42)));
+    assert_eq!(program.execute(), Ok(ExitStatus::Halted));
+  }
+
+  #[test]
+  fn run_until_event() {
+    let mut program =
+      Program::new(vec![3, 9, 1001, 9, 1, 9, 4, 9, 99, 0], vec![]);
+
+    assert_eq!(program.run_until_event(), Ok(RunState::AwaitingInput));
+    assert_eq!(program.run_until_event(), Ok(RunState::AwaitingInput));
+
+    program.io.inputs.push_back(41);
+
+    assert_eq!(program.run_until_event(), Ok(RunState::Output(42)));
+    assert_eq!(program.run_until_event(), Ok(RunState::Halted));
+  }
+
+  #[test]
+  fn limits() {
+    let stack = vec![1101, 1, 2, 7, 1105, 1, 0, 0];
+
+    let mut program = Program::new(stack.clone(), vec![]);
+    program.limits = Limits::instructions(11);
+
+    let limit = Reason::LimitExceeded(Limit::Instructions(11));
+    let error = IntcodeError { pointer: 4, opcode: 1105, reason: limit };
+    assert_eq!(program.execute(), Err(error));
+    assert_eq!(program.executed(), 11);
+
+    // The program can be resumed once its limits are raised
+    program.limits = Limits::instructions(12);
+    assert_eq!(program.step(), Ok(RunState::Running));
+    assert_eq!(program.pointer(), 0);
+
+    let mut program = Program::new(stack, vec![]);
+    program.limits = Limits::duration(Duration::from_millis(10));
+
+    let limit =
+      Reason::LimitExceeded(Limit::Duration(Duration::from_millis(10)));
+    assert_eq!(program.execute().map_err(|err| err.reason), Err(limit));
+  }
+
+  #[test]
+  fn words() {
+    // Squares its input, and outputs the result
+    let stack = vec![3, 9, 2, 9, 9, 9, 4, 9, 99, 0];
+
+    let inputs = Queue::new(vec![3_000_000_000_i64]);
+    let mut program =
+      Program::with_memory(stack.clone(), inputs, Paged::default());
+    assert_eq!(
+      program.execute_for_output(),
+      Ok(Some(9_000_000_000_000_000_000))
+    );
+
+    let inputs = Queue::new(vec![4_000_000_000_i64]);
+    let mut program =
+      Program::with_memory(stack.clone(), inputs, Paged::default());
+    let error =
+      IntcodeError { pointer: 2, opcode: 2, reason: Reason::Overflow };
+    assert_eq!(program.execute(), Err(error));
+
+    let value = BigInt::from(u128::MAX);
+    let stack = stack.into_iter().map(BigInt::from).collect();
+    let inputs = Queue::new(vec![value.clone()]);
+    let mut program = Program::with_memory(stack, inputs, Paged::default());
+    assert_eq!(program.execute_for_output(), Ok(Some(&value * &value)));
+  }
+
+  #[test]
+  fn step() {
+    let mut program = Program::new(vec![1101, 1, 2, 5, 99, 0], vec![]);
+
+    assert_eq!(program.step(), Ok(RunState::Running));
+    assert_eq!(program.stack[5], 3);
+    assert_eq!(program.step(), Ok(RunState::Halted));
+  }
 }
@@ -0,0 +1,275 @@
+use std::{
+  error::Error,
+  fmt,
+  fs::File,
+  io::{self, BufRead, BufReader, BufWriter, Write},
+  path::Path,
+};
+
+use super::{Event, IntcodeError, IntcodeIo, Program, Tracer};
+
+// A value consumed or produced by a program
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Exchange {
+  Input(i128),
+  Output(i128),
+}
+
+impl fmt::Display for Exchange {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match self {
+      Exchange::Input(value) => write!(formatter, "input {}", value),
+      Exchange::Output(value) => write!(formatter, "output {}", value),
+    }
+  }
+}
+
+// Why a replayed program did not behave as recorded, `index` being the position in the session
+//  * ReplayError::Output means the program output a value that was not recorded
+//  * ReplayError::Input means the program requested an input where an output was recorded
+//  * ReplayError::Incomplete means the program stopped before the end of the session
+#[derive(Debug, PartialEq, Clone)]
+pub enum ReplayError {
+  Program(IntcodeError),
+  Output { index: usize, value: i128, expected: Option<Exchange> },
+  Input { index: usize, expected: Exchange },
+  Incomplete { index: usize },
+}
+
+impl fmt::Display for ReplayError {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match self {
+      ReplayError::Program(err) => write!(formatter, "{}", err),
+      ReplayError::Output { index, value, expected: Some(expected) } => write!(
+        formatter,
+        "exchange {}: expected {}, found output {}",
+        index, expected, value
+      ),
+      ReplayError::Output { index, value, expected: None } => write!(
+        formatter,
+        "exchange {}: unexpected output {} after the end of the session",
+        index, value
+      ),
+      ReplayError::Input { index, expected } => write!(
+        formatter,
+        "exchange {}: expected {}, found an input request",
+        index, expected
+      ),
+      ReplayError::Incomplete { index } => {
+        write!(formatter, "exchange {}: the program stopped", index)
+      }
+    }
+  }
+}
+
+impl Error for ReplayError {}
+
+// Every value exchanged by a program with its environment, in order
+// Sessions are recorded by attaching them as the tracer of a program
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Session {
+  pub exchanges: Vec<Exchange>,
+}
+
+impl Tracer for Session {
+  fn trace(&mut self, event: &Event) -> io::Result<()> {
+    match (event.mnemonic, event.write) {
+      ("Input", Some((_, value))) => {
+        self.exchanges.push(Exchange::Input(value))
+      }
+      ("Output", _) => self.exchanges.push(Exchange::Output(event.params[0])),
+      _ => (),
+    }
+
+    Ok(())
+  }
+}
+
+impl Session {
+  pub fn new() -> Session {
+    Session::default()
+  }
+
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    self.write_to(&mut writer)?;
+    writer.flush()
+  }
+
+  pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Session> {
+    Session::read_from(BufReader::new(File::open(path)?))
+  }
+
+  // Write one exchange per line, as `in VALUE` or `out VALUE`
+  pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+    for exchange in &self.exchanges {
+      match exchange {
+        Exchange::Input(value) => writeln!(writer, "in {}", value)?,
+        Exchange::Output(value) => writeln!(writer, "out {}", value)?,
+      }
+    }
+
+    Ok(())
+  }
+
+  pub fn read_from<R: BufRead>(reader: R) -> io::Result<Session> {
+    let mut exchanges = vec![];
+
+    for line in reader.lines() {
+      let line = line?;
+      let exchange = match line.split_once(' ') {
+        Some(("in", value)) => value.parse().map(Exchange::Input),
+        Some(("out", value)) => value.parse().map(Exchange::Output),
+        _ if line.trim().is_empty() => continue,
+        _ => {
+          let message = format!("invalid exchange `{}`", line);
+          return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+      };
+
+      match exchange {
+        Ok(exchange) => exchanges.push(exchange),
+        Err(_) => {
+          let message = format!("invalid value in `{}`", line);
+          return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+      }
+    }
+
+    Ok(Session { exchanges })
+  }
+
+  // Run `stack` again, feeding the recorded inputs back and checking every output against the recorded ones
+  // The program must consume the whole session, but can be left waiting for more inputs
+  pub fn replay(&self, stack: Vec<i128>) -> Result<(), ReplayError> {
+    let io = Replay { exchanges: &self.exchanges, index: 0, error: None };
+    let mut program = Program::with_io(stack, io);
+    let result = program.run_until_blocked();
+
+    if let Some(error) = program.io.error.take() {
+      return Err(error);
+    }
+    result.map_err(ReplayError::Program)?;
+
+    match program.io.index {
+      index if index < self.exchanges.len() => {
+        Err(ReplayError::Incomplete { index })
+      }
+      _ => Ok(()),
+    }
+  }
+}
+
+// IO checking a program against a session, keeping the first divergence found
+struct Replay<'a> {
+  exchanges: &'a [Exchange],
+  index: usize,
+  error: Option<ReplayError>,
+}
+
+impl Replay<'_> {
+  fn diverge(&mut self, error: ReplayError) -> io::Error {
+    self.error = Some(error);
+    io::ErrorKind::InvalidData.into()
+  }
+}
+
+impl IntcodeIo for Replay<'_> {
+  fn read(&mut self) -> io::Result<Option<i128>> {
+    let index = self.index;
+
+    match self.exchanges.get(index) {
+      Some(Exchange::Input(value)) => {
+        self.index += 1;
+        Ok(Some(*value))
+      }
+      Some(expected) => {
+        let expected = *expected;
+        Err(self.diverge(ReplayError::Input { index, expected }))
+      }
+      None => Ok(None),
+    }
+  }
+
+  fn write(&mut self, value: i128) -> io::Result<()> {
+    let index = self.index;
+
+    match self.exchanges.get(index) {
+      Some(Exchange::Output(expected)) if *expected == value => {
+        self.index += 1;
+        Ok(())
+      }
+      expected => {
+        let expected = expected.copied();
+        Err(self.diverge(ReplayError::Output { index, value, expected }))
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Exchange, ReplayError, Session};
+  use crate::util::intcode::{Program, RunState};
+
+  // Outputs twice every input, until 0 is given
+  const STACK: [i128; 16] =
+    [3, 15, 1006, 15, 14, 102, 2, 15, 15, 4, 15, 1105, 1, 0, 99, 0];
+
+  #[test]
+  fn record() {
+    let program = Program::new(STACK.to_vec(), vec![1, 2, 0]);
+    let mut program = program.with_tracer(Session::new());
+
+    assert_eq!(program.run_until_blocked(), Ok(RunState::Halted));
+
+    let session = program.tracer;
+    assert_eq!(
+      session.exchanges,
+      vec![
+        Exchange::Input(1),
+        Exchange::Output(2),
+        Exchange::Input(2),
+        Exchange::Output(4),
+        Exchange::Input(0),
+      ]
+    );
+
+    let mut file = vec![];
+    session.write_to(&mut file).unwrap();
+    assert_eq!(file, b"in 1\nout 2\nin 2\nout 4\nin 0\n");
+    assert_eq!(Session::read_from(&file[..]).unwrap(), session);
+
+    assert_eq!(session.replay(STACK.to_vec()), Ok(()));
+  }
+
+  #[test]
+  fn divergences() {
+    let session =
+      |exchanges: &[Exchange]| Session { exchanges: exchanges.to_vec() };
+
+    let recorded = session(&[Exchange::Input(1), Exchange::Output(3)]);
+    assert_eq!(
+      recorded.replay(STACK.to_vec()),
+      Err(ReplayError::Output {
+        index: 1,
+        value: 2,
+        expected: Some(Exchange::Output(3))
+      })
+    );
+
+    let recorded = session(&[Exchange::Output(2)]);
+    assert_eq!(
+      recorded.replay(STACK.to_vec()),
+      Err(ReplayError::Input { index: 0, expected: Exchange::Output(2) })
+    );
+
+    let recorded = session(&[Exchange::Input(0), Exchange::Input(1)]);
+    assert_eq!(
+      recorded.replay(STACK.to_vec()),
+      Err(ReplayError::Incomplete { index: 1 })
+    );
+
+    assert!(Session::read_from(&b"in 1\nthrough 2\n"[..]).is_err());
+  }
+}
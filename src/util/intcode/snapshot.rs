@@ -0,0 +1,233 @@
+use std::{
+  collections::HashMap,
+  fs::File,
+  io::{self, BufRead, BufReader, BufWriter, Write},
+  path::Path,
+};
+
+// Whole state of a machine using in-memory queues, which can be saved and restored later on
+// Only the non-zero words of the heap are kept, addressed from the end of the stack
+// The configuration of the program (limits, extensions, engine, tracer) is not part of it
+#[derive(Debug, PartialEq, Clone)]
+pub struct Snapshot {
+  pub pointer: usize,
+  pub base: usize,
+  pub stack: Vec<i128>,
+  pub heap: Vec<(usize, i128)>,
+  pub inputs: Vec<i128>,
+  pub outputs: Vec<i128>,
+  pub retval: Option<i128>,
+  pub paused: bool,
+  pub halted: bool,
+  pub executed: u64,
+}
+
+fn invalid(message: String) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn join<T: ToString>(values: &[T]) -> String {
+  let values: Vec<String> = values.iter().map(T::to_string).collect();
+  values.join(",")
+}
+
+fn split(values: &str) -> impl Iterator<Item = &str> {
+  values.split(',').filter(|value| !value.is_empty())
+}
+
+fn parse<T: std::str::FromStr>(key: &str, value: &str) -> io::Result<T> {
+  value
+    .parse()
+    .map_err(|_| invalid(format!("invalid value `{}` for `{}`", value, key)))
+}
+
+impl Snapshot {
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    self.write_to(&mut writer)?;
+    writer.flush()
+  }
+
+  pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Snapshot> {
+    Snapshot::read_from(BufReader::new(File::open(path)?))
+  }
+
+  // Write the snapshot as one `key value` line per field, lists being comma-separated:
+  //
+  //   pointer 6
+  //   base 0
+  //   retval none
+  //   paused 0
+  //   halted 0
+  //   executed 3
+  //   stack 3,9,1002,9,2,9,4,9,99,0
+  //   heap 12=42,2048=-1
+  //   inputs 1,2
+  //   outputs
+  pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+    let retval = match self.retval {
+      Some(retval) => retval.to_string(),
+      None => "none".to_string(),
+    };
+    let heap: Vec<String> = self
+      .heap
+      .iter()
+      .map(|(address, value)| format!("{}={}", address, value))
+      .collect();
+
+    writeln!(writer, "pointer {}", self.pointer)?;
+    writeln!(writer, "base {}", self.base)?;
+    writeln!(writer, "retval {}", retval)?;
+    writeln!(writer, "paused {}", self.paused as u8)?;
+    writeln!(writer, "halted {}", self.halted as u8)?;
+    writeln!(writer, "executed {}", self.executed)?;
+    writeln!(writer, "stack {}", join(&self.stack))?;
+    writeln!(writer, "heap {}", heap.join(","))?;
+    writeln!(writer, "inputs {}", join(&self.inputs))?;
+    writeln!(writer, "outputs {}", join(&self.outputs))
+  }
+
+  pub fn read_from<R: BufRead>(reader: R) -> io::Result<Snapshot> {
+    let mut fields = HashMap::new();
+
+    for line in reader.lines() {
+      let line = line?;
+      let line = line.trim();
+      if line.is_empty() {
+        continue;
+      }
+
+      let (key, value) = match line.find(' ') {
+        Some(space) => (&line[..space], line[space + 1..].trim()),
+        None => (line, ""),
+      };
+      fields.insert(key.to_string(), value.to_string());
+    }
+
+    let field = |key: &str| match fields.get(key) {
+      Some(value) => Ok(value.as_str()),
+      None => Err(invalid(format!("missing `{}`", key))),
+    };
+    let list = |key: &str| -> io::Result<Vec<i128>> {
+      split(field(key)?).map(|value| parse(key, value)).collect()
+    };
+    let flag = |key: &str| match field(key)? {
+      "0" => Ok(false),
+      "1" => Ok(true),
+      value => Err(invalid(format!("invalid value `{}` for `{}`", value, key))),
+    };
+
+    let heap = split(field("heap")?)
+      .map(|cell| match cell.find('=') {
+        Some(equal) => Ok((
+          parse("heap", &cell[..equal])?,
+          parse("heap", &cell[equal + 1..])?,
+        )),
+        None => Err(invalid(format!("invalid value `{}` for `heap`", cell))),
+      })
+      .collect::<io::Result<_>>()?;
+
+    Ok(Snapshot {
+      pointer: parse("pointer", field("pointer")?)?,
+      base: parse("base", field("base")?)?,
+      stack: list("stack")?,
+      heap,
+      inputs: list("inputs")?,
+      outputs: list("outputs")?,
+      retval: match field("retval")? {
+        "none" => None,
+        value => Some(parse("retval", value)?),
+      },
+      paused: flag("paused")?,
+      halted: flag("halted")?,
+      executed: parse("executed", field("executed")?)?,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Snapshot;
+  use crate::util::intcode::{
+    IntcodeError, Limit, Limits, Program, Reason, RunState,
+  };
+
+  #[test]
+  fn round_trip() {
+    // Doubles every input, keeping a running total far away in the heap
+    let stack =
+      vec![3, 100, 1002, 100, 2, 100, 1, 100, 5000, 5000, 4, 100, 1105, 1, 0];
+    let mut program = Program::new(stack, vec![1, 2]);
+
+    assert_eq!(program.run_until_event(), Ok(RunState::Output(2)));
+
+    let snapshot = program.snapshot();
+    let mut text = vec![];
+    snapshot.write_to(&mut text).unwrap();
+
+    assert_eq!(
+      String::from_utf8(text.clone()).unwrap(),
+      "pointer 12\nbase 0\nretval 2\npaused 1\nhalted 0\nexecuted 4\n\
+       stack 3,100,1002,100,2,100,1,100,5000,5000,4,100,1105,1,0\n\
+       heap 85=2,4985=2\ninputs 2\noutputs \n"
+    );
+
+    let snapshot = Snapshot::read_from(text.as_slice()).unwrap();
+    let mut programs = [program, Program::restore(&snapshot)];
+
+    for program in programs.iter_mut() {
+      assert_eq!(program.run_until_blocked(), Ok(RunState::AwaitingInput));
+      assert_eq!(program.io.outputs, vec![4]);
+    }
+
+    let [_, mut restored] = programs;
+    restored.io.inputs.push_back(3);
+    assert_eq!(restored.run_until_event(), Ok(RunState::Output(6)));
+    assert_eq!(restored.peek(5000), 12);
+  }
+
+  #[test]
+  fn restore_into() {
+    // Outputs 1, 2, 3... forever
+    let stack = vec![1001, 9, 1, 9, 4, 9, 1105, 1, 0, 0];
+    let mut program = Program::new(stack, vec![]);
+    program.limits = Limits::instructions(10);
+
+    assert_eq!(program.run_until_event(), Ok(RunState::Output(1)));
+    let snapshot = program.snapshot();
+    assert_eq!(snapshot.executed, 2);
+
+    // The configuration of the program is kept, and the instructions executed before the snapshot still count
+    program.run_until_event().unwrap();
+    program.restore_into(&snapshot);
+    assert_eq!(program.executed(), 2);
+
+    let limit = Reason::LimitExceeded(Limit::Instructions(10));
+    let error = IntcodeError { pointer: 4, opcode: 4, reason: limit };
+    assert_eq!(program.run_until_blocked(), Err(error));
+
+    // Restoring a program from scratch drops its configuration
+    let mut restored = Program::restore(&snapshot);
+    assert_eq!(restored.executed(), 2);
+    assert_eq!(restored.run_until_event(), Ok(RunState::Output(2)));
+    assert_eq!(restored.limits, Limits::default());
+  }
+
+  #[test]
+  fn errors() {
+    let data = vec![
+      ("pointer 0", "missing `base`"),
+      ("pointer x\nbase 0", "invalid value `x` for `pointer`"),
+    ];
+
+    for (text, message) in data {
+      let text = format!(
+        "{}\nretval none\npaused 0\nhalted 0\nexecuted 0\nstack\nheap\n\
+         inputs\noutputs",
+        text
+      );
+      let err = Snapshot::read_from(text.as_bytes()).unwrap_err();
+      assert_eq!(err.to_string(), message);
+    }
+  }
+}
@@ -0,0 +1,582 @@
+use std::{
+  collections::{BTreeMap, HashMap, VecDeque},
+  convert::TryFrom,
+  error::Error,
+  fmt,
+  ops::RangeInclusive,
+};
+
+use super::{
+  instruction::Instruction, limits::Budget, mode::Mode, opcode::Opcode::*,
+  Extensions, IntcodeError, Limits, Reason,
+};
+
+// Value computed by a program run symbolically
+//  * Expr::Var is a value chosen by the caller, either a memory cell or an input
+//  * Expr::Unknown is a value read through an address depending on a variable, which cannot be evaluated
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expr {
+  Const(i128),
+  Var(&'static str),
+  Unknown,
+  Add(Box<Expr>, Box<Expr>),
+  Mul(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+  // Build `lhs + rhs`, folding constants, None being returned on overflow
+  pub fn sum(lhs: Expr, rhs: Expr) -> Option<Expr> {
+    match (lhs, rhs) {
+      (Expr::Const(a), Expr::Const(b)) => a.checked_add(b).map(Expr::Const),
+      (Expr::Const(0), expr) | (expr, Expr::Const(0)) => Some(expr),
+      (lhs, rhs) => Some(Expr::Add(Box::new(lhs), Box::new(rhs))),
+    }
+  }
+
+  // Build `lhs * rhs`, folding constants, None being returned on overflow
+  pub fn product(lhs: Expr, rhs: Expr) -> Option<Expr> {
+    match (lhs, rhs) {
+      (Expr::Const(a), Expr::Const(b)) => a.checked_mul(b).map(Expr::Const),
+      (Expr::Const(0), _) | (_, Expr::Const(0)) => Some(Expr::Const(0)),
+      (Expr::Const(1), expr) | (expr, Expr::Const(1)) => Some(expr),
+      (lhs, rhs) => Some(Expr::Mul(Box::new(lhs), Box::new(rhs))),
+    }
+  }
+
+  // Value of the expression for the given variables, if they are all known and nothing overflows
+  pub fn eval(&self, values: &[(&str, i128)]) -> Option<i128> {
+    match self {
+      Expr::Const(value) => Some(*value),
+      Expr::Var(name) => {
+        values.iter().find(|(var, _)| var == name).map(|(_, value)| *value)
+      }
+      Expr::Unknown => None,
+      Expr::Add(lhs, rhs) => lhs.eval(values)?.checked_add(rhs.eval(values)?),
+      Expr::Mul(lhs, rhs) => lhs.eval(values)?.checked_mul(rhs.eval(values)?),
+    }
+  }
+
+  // Rewrite the expression as `constant + a * x + b * y + ...`, unless it is not linear
+  pub fn affine(&self) -> Option<Affine> {
+    match self {
+      Expr::Const(value) => Some(Affine::constant(*value)),
+      Expr::Var(name) => {
+        let mut affine = Affine::constant(0);
+        affine.terms.insert(name, 1);
+        Some(affine)
+      }
+      Expr::Unknown => None,
+      Expr::Add(lhs, rhs) => lhs.affine()?.add(&rhs.affine()?),
+      Expr::Mul(lhs, rhs) => {
+        let (lhs, rhs) = (lhs.affine()?, rhs.affine()?);
+
+        match (lhs.terms.is_empty(), rhs.terms.is_empty()) {
+          (true, _) => rhs.scale(lhs.constant),
+          (_, true) => lhs.scale(rhs.constant),
+          _ => None,
+        }
+      }
+    }
+  }
+}
+
+impl fmt::Display for Expr {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match self {
+      Expr::Const(value) => write!(formatter, "{}", value),
+      Expr::Var(name) => write!(formatter, "{}", name),
+      Expr::Unknown => write!(formatter, "?"),
+      Expr::Add(lhs, rhs) => write!(formatter, "({} + {})", lhs, rhs),
+      Expr::Mul(lhs, rhs) => write!(formatter, "({} * {})", lhs, rhs),
+    }
+  }
+}
+
+// Linear combination of variables, without any null coefficient
+#[derive(Debug, PartialEq, Clone)]
+pub struct Affine {
+  pub constant: i128,
+  pub terms: BTreeMap<&'static str, i128>,
+}
+
+impl Affine {
+  fn constant(value: i128) -> Affine {
+    Affine { constant: value, terms: BTreeMap::new() }
+  }
+
+  fn add(&self, other: &Affine) -> Option<Affine> {
+    let mut sum = Affine::constant(self.constant.checked_add(other.constant)?);
+    sum.terms = self.terms.clone();
+
+    for (name, coefficient) in &other.terms {
+      let entry = sum.terms.entry(name).or_insert(0);
+      *entry = entry.checked_add(*coefficient)?;
+    }
+    sum.terms.retain(|_, coefficient| *coefficient != 0);
+
+    Some(sum)
+  }
+
+  fn scale(&self, factor: i128) -> Option<Affine> {
+    let mut product = Affine::constant(self.constant.checked_mul(factor)?);
+
+    if factor != 0 {
+      for (name, coefficient) in &self.terms {
+        product.terms.insert(name, coefficient.checked_mul(factor)?);
+      }
+    }
+
+    Some(product)
+  }
+
+  // Find values within `domains` for which the combination equals `target`
+  // Every variable but one is enumerated, the last one being computed directly
+  fn solve(
+    &self, target: i128, domains: &[(&'static str, RangeInclusive<i128>)],
+  ) -> Option<Vec<i128>> {
+    if self.terms.keys().any(|name| domains.iter().all(|(var, _)| var != name))
+    {
+      return None;
+    }
+
+    let solved =
+      domains.iter().rposition(|(name, _)| self.terms.contains_key(name));
+
+    let solved = match solved {
+      Some(index) => index,
+      None if self.constant == target => {
+        return Some(domains.iter().map(|(_, range)| *range.start()).collect());
+      }
+      None => return None,
+    };
+
+    let (name, range) = &domains[solved];
+    let coefficient = self.terms[name];
+    let mut others = domains.to_vec();
+    let mut found = None;
+
+    others.remove(solved);
+
+    let values = enumerate(&others, |values| {
+      let mut rest = target.checked_sub(self.constant)?;
+      for (name, value) in values {
+        let term = self.terms.get(name).copied().unwrap_or_default();
+        rest = rest.checked_sub(term.checked_mul(*value)?)?;
+      }
+
+      found = Some(rest / coefficient)
+        .filter(|value| rest % coefficient == 0 && range.contains(value));
+
+      Some(found.is_some())
+    })?;
+
+    let mut values: Vec<i128> = values.into_iter().map(|(_, v)| v).collect();
+    values.insert(solved, found?);
+
+    Some(values)
+  }
+}
+
+// Find values within `domains` (in order) for which `expr` equals `target`
+// Linear expressions are solved, others are evaluated for every combination of values
+pub fn solve(
+  expr: &Expr, target: i128, domains: &[(&'static str, RangeInclusive<i128>)],
+) -> Option<Vec<i128>> {
+  if let Some(affine) = expr.affine() {
+    return affine.solve(target, domains);
+  }
+
+  let values = enumerate(domains, |values| Some(expr.eval(values)? == target))?;
+
+  Some(values.into_iter().map(|(_, value)| value).collect())
+}
+
+// Try every combination of values, the first domain varying the slowest, until `check` accepts one
+fn enumerate<F>(
+  domains: &[(&'static str, RangeInclusive<i128>)], mut check: F,
+) -> Option<Vec<(&'static str, i128)>>
+where
+  F: FnMut(&[(&'static str, i128)]) -> Option<bool>,
+{
+  fn search<F>(
+    domains: &[(&'static str, RangeInclusive<i128>)],
+    values: &mut Vec<(&'static str, i128)>, check: &mut F,
+  ) -> bool
+  where
+    F: FnMut(&[(&'static str, i128)]) -> Option<bool>,
+  {
+    match domains.split_first() {
+      None => check(values).unwrap_or(false),
+      Some(((name, range), rest)) => {
+        for value in range.clone() {
+          values.push((name, value));
+          if search(rest, values, check) {
+            return true;
+          }
+          values.pop();
+        }
+
+        false
+      }
+    }
+  }
+
+  let mut values = vec![];
+
+  if search(domains, &mut values, &mut check) {
+    Some(values)
+  } else {
+    None
+  }
+}
+
+// Why a program could not be run symbolically
+//  * SymbolicError::Program means the program failed, as it would have if run with concrete values
+//  * SymbolicError::Symbolic means an instruction, a branch or a write address depends on a variable
+#[derive(Debug, PartialEq, Clone)]
+pub enum SymbolicError {
+  Program(IntcodeError),
+  Symbolic { pointer: usize },
+}
+
+impl fmt::Display for SymbolicError {
+  fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match self {
+      SymbolicError::Program(err) => write!(formatter, "{}", err),
+      SymbolicError::Symbolic { pointer } => write!(
+        formatter,
+        "control flow depends on a symbolic value at address {}",
+        pointer
+      ),
+    }
+  }
+}
+
+impl Error for SymbolicError {}
+
+// Program whose memory cells and inputs can be variables, every value being computed as an expression of those
+// Only arithmetic is symbolic: instructions, jumps, comparisons and write addresses must stay concrete
+#[derive(Debug, Clone)]
+pub struct Symbolic {
+  pointer: usize,
+  base: usize,
+  heap: HashMap<usize, Expr>,
+  executed: u64,
+  budget: Budget,
+  pub stack: Vec<Expr>,
+  pub inputs: VecDeque<Expr>,
+  pub outputs: Vec<Expr>,
+  pub limits: Limits,
+}
+
+impl Symbolic {
+  pub fn new(stack: &[i128]) -> Symbolic {
+    Symbolic {
+      pointer: 0,
+      base: 0,
+      heap: HashMap::new(),
+      executed: 0,
+      budget: Budget::default(),
+      stack: stack.iter().copied().map(Expr::Const).collect(),
+      inputs: VecDeque::new(),
+      outputs: vec![],
+      limits: Limits::default(),
+    }
+  }
+
+  // Make the memory cell at `address` the variable `name`
+  pub fn bind(&mut self, address: usize, name: &'static str) {
+    self.set(address, Expr::Var(name));
+  }
+
+  // Queue the variable `name` as the next input
+  pub fn input(&mut self, name: &'static str) {
+    self.inputs.push_back(Expr::Var(name));
+  }
+
+  // Read the expression held in memory at the provided address
+  pub fn get(&self, address: usize) -> Expr {
+    if address >= self.stack.len() {
+      let address = address - self.stack.len();
+      self.heap.get(&address).cloned().unwrap_or(Expr::Const(0))
+    } else {
+      self.stack[address].clone()
+    }
+  }
+
+  fn set(&mut self, address: usize, value: Expr) {
+    if address >= self.stack.len() {
+      self.heap.insert(address - self.stack.len(), value);
+    } else {
+      self.stack[address] = value;
+    }
+  }
+
+  // Number of instructions executed so far
+  pub fn executed(&self) -> u64 {
+    self.executed
+  }
+
+  // Address of the next instruction to be executed, and relative base
+  pub fn pointer(&self) -> usize {
+    self.pointer
+  }
+
+  pub fn base(&self) -> usize {
+    self.base
+  }
+
+  // Every heap word written to, addressed from the end of the stack
+  pub(super) fn heap(&self) -> &HashMap<usize, Expr> {
+    &self.heap
+  }
+
+  // Run the program until it halts, or until it runs out of its limits
+  pub fn run(&mut self) -> Result<(), SymbolicError> {
+    let extensions = Extensions::new();
+
+    loop {
+      let pointer = self.pointer;
+      let opcode = self.concrete(self.get(pointer))?;
+      self.check_limits(opcode)?;
+      let read = |address| match self.get(address) {
+        Expr::Const(value) => value,
+        _ => 0,
+      };
+
+      let instruction = Instruction::decode(pointer, read, &extensions)
+        .map_err(|reason| self.fault(opcode, reason))?;
+      let modes = instruction.op.modes();
+      let params: Vec<Expr> = (1..instruction.size())
+        .map(|offset| self.get(pointer + offset))
+        .collect();
+
+      let value =
+        |index: usize| self.deref(modes[index], &params[index], opcode);
+      let mut next = instruction.next(pointer);
+
+      match instruction.op {
+        Halt(..) => {
+          self.executed += 1;
+          return Ok(());
+        }
+
+        Add(..) | Mul(..) => {
+          let (v1, v2) = (value(0)?, value(1)?);
+          let r = self.address(modes[2], &params[2], opcode)?;
+          let result = match instruction.op {
+            Add(..) => Expr::sum(v1, v2),
+            _ => Expr::product(v1, v2),
+          };
+          let result =
+            result.ok_or_else(|| self.fault(opcode, Reason::Overflow))?;
+          self.set(r, result);
+        }
+
+        Input(..) => {
+          let r = self.address(modes[0], &params[0], opcode)?;
+          let input = self
+            .inputs
+            .pop_front()
+            .ok_or_else(|| self.fault(opcode, Reason::MissingInput))?;
+          self.set(r, input);
+        }
+
+        Output(..) => {
+          let output = value(0)?;
+          self.outputs.push(output);
+        }
+
+        JumpIf(..) | JumpUnless(..) => {
+          let condition = self.concrete(value(0)?)? != 0;
+          if condition == matches!(instruction.op, JumpIf(..)) {
+            next = Some(self.to_address(self.concrete(value(1)?)?, opcode)?);
+          }
+        }
+
+        IfLess(..) | IfEquals(..) => {
+          // The address is resolved before the operands, as the interpreter does
+          let r = self.address(modes[2], &params[2], opcode)?;
+          let (v1, v2) = (self.concrete(value(0)?)?, self.concrete(value(1)?)?);
+          let result = match instruction.op {
+            IfLess(..) => v1 < v2,
+            _ => v1 == v2,
+          };
+          self.set(r, Expr::Const(result as i128));
+        }
+
+        SetBase(..) => {
+          let offset = self.concrete(value(0)?)?;
+          self.base = self.offset(offset, opcode)?;
+        }
+
+        Extended(..) => {
+          return Err(self.fault(opcode, Reason::InvalidOpcode));
+        }
+      }
+
+      self.pointer =
+        next.ok_or_else(|| self.fault(opcode, Reason::Overflow))?;
+      self.executed += 1;
+    }
+  }
+
+  fn check_limits(&mut self, opcode: i128) -> Result<(), SymbolicError> {
+    match self.budget.exceeded(&self.limits, self.executed) {
+      Some(limit) => Err(self.fault(opcode, Reason::LimitExceeded(limit))),
+      None => Ok(()),
+    }
+  }
+
+  fn fault(&self, opcode: i128, reason: Reason) -> SymbolicError {
+    let pointer = self.pointer;
+    SymbolicError::Program(IntcodeError { pointer, opcode, reason })
+  }
+
+  // Value of an expression that must not depend on any variable
+  fn concrete(&self, expr: Expr) -> Result<i128, SymbolicError> {
+    match expr {
+      Expr::Const(value) => Ok(value),
+      _ => Err(SymbolicError::Symbolic { pointer: self.pointer }),
+    }
+  }
+
+  // Operand value, reads through symbolic addresses being unknown
+  fn deref(
+    &self, mode: Mode, param: &Expr, opcode: i128,
+  ) -> Result<Expr, SymbolicError> {
+    match (mode, param) {
+      (Mode::Immediate, param) => Ok(param.clone()),
+      (Mode::Position, Expr::Const(value)) => {
+        Ok(self.get(self.to_address(*value, opcode)?))
+      }
+      (Mode::Relative, Expr::Const(value)) => {
+        Ok(self.get(self.offset(*value, opcode)?))
+      }
+      _ => Ok(Expr::Unknown),
+    }
+  }
+
+  // Address written to, which must be known
+  fn address(
+    &self, mode: Mode, param: &Expr, opcode: i128,
+  ) -> Result<usize, SymbolicError> {
+    let value = self.concrete(param.clone())?;
+
+    match mode {
+      Mode::Position => self.to_address(value, opcode),
+      Mode::Relative => self.offset(value, opcode),
+      Mode::Immediate => Err(self.fault(opcode, Reason::ImmediateWrite)),
+    }
+  }
+
+  fn to_address(
+    &self, value: i128, opcode: i128,
+  ) -> Result<usize, SymbolicError> {
+    if value < 0 {
+      return Err(self.fault(opcode, Reason::NegativeAddress));
+    }
+
+    usize::try_from(value).map_err(|_| self.fault(opcode, Reason::Overflow))
+  }
+
+  fn offset(&self, value: i128, opcode: i128) -> Result<usize, SymbolicError> {
+    let address = (self.base as i128)
+      .checked_add(value)
+      .ok_or_else(|| self.fault(opcode, Reason::Overflow))?;
+
+    self.to_address(address, opcode)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use super::{solve, Expr, Symbolic, SymbolicError};
+  use crate::util::intcode::{assemble, IntcodeError, Limit, Limits, Reason};
+
+  #[test]
+  fn linear() {
+    // 3 * (x + 4) + 2 * y
+    let source = "
+      Add [x], #4, [t]
+      Mul [t], #3, [t]
+      Mul [y], #2, [u]
+      Add [t], [u], [t]
+      Halt
+      x:  .data 0
+      y:  .data 0
+      t:  .data 0
+      u:  .data 0
+    ";
+
+    let mut symbolic = Symbolic::new(&assemble(source).unwrap());
+    symbolic.bind(17, "x");
+    symbolic.bind(18, "y");
+
+    assert_eq!(symbolic.run(), Ok(()));
+
+    let expr = symbolic.get(19);
+    assert_eq!(expr.to_string(), "(((x + 4) * 3) + (y * 2))");
+
+    let affine = expr.affine().unwrap();
+    assert_eq!(affine.constant, 12);
+    assert_eq!(
+      affine.terms.into_iter().collect::<Vec<_>>(),
+      vec![("x", 3), ("y", 2)]
+    );
+
+    let domains = [("x", 0..=9), ("y", 0..=9)];
+    assert_eq!(solve(&expr, 31, &domains), Some(vec![1, 8]));
+    assert_eq!(solve(&expr, 13, &domains), None);
+  }
+
+  #[test]
+  fn non_linear() {
+    // Squares its input, then branches on it
+    let source = "
+      Input [x]
+      Mul [x], [x], [x]
+      Output [x]
+      JumpIf [x], #0
+      Halt
+      x:  .data 0
+    ";
+
+    let mut symbolic = Symbolic::new(&assemble(source).unwrap());
+    symbolic.input("x");
+
+    assert_eq!(symbolic.run(), Err(SymbolicError::Symbolic { pointer: 8 }));
+
+    let output = symbolic.outputs[0].clone();
+    assert_eq!(output.affine(), None);
+    assert_eq!(solve(&output, 49, &[("x", 0..=99)]), Some(vec![7]));
+
+    // Reads through a symbolic address cannot be solved
+    let unknown = Expr::sum(Expr::Unknown, Expr::Var("x")).unwrap();
+    assert_eq!(solve(&unknown, 0, &[("x", 0..=9)]), None);
+  }
+
+  #[test]
+  fn limits() {
+    // Loops forever
+    let mut symbolic = Symbolic::new(&[1105, 1, 0]);
+    symbolic.limits = Limits::instructions(100);
+
+    let reason = Reason::LimitExceeded(Limit::Instructions(100));
+    let error = IntcodeError { pointer: 0, opcode: 1105, reason };
+    assert_eq!(symbolic.run(), Err(SymbolicError::Program(error)));
+    assert_eq!(symbolic.executed(), 100);
+
+    let mut symbolic = Symbolic::new(&[1105, 1, 0]);
+    symbolic.limits = Limits::duration(Duration::from_millis(10));
+
+    let reason =
+      Reason::LimitExceeded(Limit::Duration(Duration::from_millis(10)));
+    let error = IntcodeError { pointer: 0, opcode: 1105, reason };
+    assert_eq!(symbolic.run(), Err(SymbolicError::Program(error)));
+    // The clock is only read every 1024 instructions
+    assert_eq!(symbolic.executed() % 1024, 0);
+  }
+}
@@ -0,0 +1,193 @@
+use std::{
+  fmt::Display,
+  io::{self, Write},
+};
+
+// Everything an executed instruction did
+//  * `params` holds the resolved operands: the values read, and the address for the written operand
+//  * `write` is the memory cell written by the instruction, with its new value
+//  * `base` is the new relative base, when the instruction changed it
+#[derive(Debug, PartialEq, Clone)]
+pub struct Event<W = i128> {
+  pub address: usize,
+  pub opcode: W,
+  pub mnemonic: &'static str,
+  pub arity: usize,
+  pub params: [W; 3],
+  pub write: Option<(usize, W)>,
+  pub base: Option<usize>,
+}
+
+impl<W> Event<W> {
+  pub fn operands(&self) -> &[W] {
+    &self.params[..self.arity]
+  }
+}
+
+// Observer notified of every instruction executed by a program
+// Instructions blocked on an input are only reported once they were actually executed
+pub trait Tracer<W = i128> {
+  // Tracers disabling this do not even get events built for them
+  const ENABLED: bool = true;
+
+  fn trace(&mut self, event: &Event<W>) -> io::Result<()>;
+}
+
+// Default tracer, ignoring everything
+impl<W> Tracer<W> for () {
+  const ENABLED: bool = false;
+
+  fn trace(&mut self, _: &Event<W>) -> io::Result<()> {
+    Ok(())
+  }
+}
+
+// Optional tracer, only notified when present
+impl<W, T: Tracer<W>> Tracer<W> for Option<T> {
+  const ENABLED: bool = T::ENABLED;
+
+  fn trace(&mut self, event: &Event<W>) -> io::Result<()> {
+    match self {
+      Some(tracer) => tracer.trace(event),
+      None => Ok(()),
+    }
+  }
+}
+
+// Pair of tracers, both notified of every instruction
+impl<W, A: Tracer<W>, B: Tracer<W>> Tracer<W> for (A, B) {
+  const ENABLED: bool = A::ENABLED || B::ENABLED;
+
+  fn trace(&mut self, event: &Event<W>) -> io::Result<()> {
+    if A::ENABLED {
+      self.0.trace(event)?;
+    }
+    if B::ENABLED {
+      self.1.trace(event)?;
+    }
+    Ok(())
+  }
+}
+
+impl<W: Clone> Tracer<W> for Vec<Event<W>> {
+  fn trace(&mut self, event: &Event<W>) -> io::Result<()> {
+    self.push(event.clone());
+    Ok(())
+  }
+}
+
+// Write one line per instruction, meant to be compared with `diff`:
+//
+//   0004 Mul 3 4 9 [9]=12
+//   0008 SetBase 7 rb=1007
+#[derive(Debug)]
+pub struct TraceWriter<O> {
+  writer: O,
+}
+
+impl<O: Write> TraceWriter<O> {
+  pub fn new(writer: O) -> TraceWriter<O> {
+    TraceWriter { writer }
+  }
+}
+
+impl<W: Display, O: Write> Tracer<W> for TraceWriter<O> {
+  fn trace(&mut self, event: &Event<W>) -> io::Result<()> {
+    write!(self.writer, "{:04} {}", event.address, event.mnemonic)?;
+
+    for operand in event.operands() {
+      write!(self.writer, " {}", operand)?;
+    }
+    if let Some((address, value)) = &event.write {
+      write!(self.writer, " [{}]={}", address, value)?;
+    }
+    if let Some(base) = event.base {
+      write!(self.writer, " rb={}", base)?;
+    }
+
+    writeln!(self.writer)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Event, TraceWriter};
+  use crate::util::intcode::{Program, RunState};
+
+  #[test]
+  fn events() {
+    let stack = vec![109, 9, 21101, 2, 3, 0, 204, 0, 99, 0];
+    let mut program = Program::new(stack, vec![]).with_tracer(vec![]);
+
+    assert_eq!(program.run_until_blocked(), Ok(RunState::Halted));
+    assert_eq!(
+      program.tracer,
+      vec![
+        Event {
+          address: 0,
+          opcode: 109,
+          mnemonic: "SetBase",
+          arity: 1,
+          params: [9, 0, 0],
+          write: None,
+          base: Some(9),
+        },
+        Event {
+          address: 2,
+          opcode: 21101,
+          mnemonic: "Add",
+          arity: 3,
+          params: [2, 3, 9],
+          write: Some((9, 5)),
+          base: None,
+        },
+        Event {
+          address: 6,
+          opcode: 204,
+          mnemonic: "Output",
+          arity: 1,
+          params: [5, 0, 0],
+          write: None,
+          base: None,
+        },
+        Event {
+          address: 8,
+          opcode: 99,
+          mnemonic: "Halt",
+          arity: 0,
+          params: [0, 0, 0],
+          write: None,
+          base: None,
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn untaken_jump() {
+    // The target of the jump is a negative address, but the jump is never taken
+    let stack = vec![1106, 1, -4, 6, 1, -8, 104, 7, 99];
+
+    let mut program = Program::new(stack.clone(), vec![]);
+    let mut traced = Program::new(stack, vec![]).with_tracer(vec![]);
+
+    assert_eq!(program.run_until_blocked(), Ok(RunState::Halted));
+    assert_eq!(traced.run_until_blocked(), Ok(RunState::Halted));
+    assert_eq!(traced.io.outputs, program.io.outputs);
+    assert_eq!(traced.tracer[0].params, [1, -4, 0]);
+    assert_eq!(traced.tracer[1].params, [1, -8, 0]);
+  }
+
+  #[test]
+  fn writer() {
+    let stack = vec![3, 9, 1002, 9, 2, 9, 4, 9, 99, 0];
+    let mut program =
+      Program::new(stack, vec![21]).with_tracer(TraceWriter::new(vec![]));
+
+    assert_eq!(program.execute_for_output(), Ok(Some(42)));
+    assert_eq!(
+      String::from_utf8(program.tracer.writer).unwrap(),
+      "0000 Input 9 [9]=21\n0002 Mul 21 2 9 [9]=42\n0006 Output 42\n"
+    );
+  }
+}
@@ -0,0 +1,41 @@
+use std::{
+  fmt::{Debug, Display},
+  str::FromStr,
+};
+
+use num::{
+  traits::{CheckedAdd, CheckedMul},
+  FromPrimitive, One, ToPrimitive, Zero,
+};
+
+// Value held by a memory cell, with everything instructions need to operate on it
+// Implemented by primitive integers (i64, i128, ...) as well as num::BigInt
+pub trait Word:
+  Clone
+  + Debug
+  + Display
+  + FromStr
+  + PartialOrd
+  + Zero
+  + One
+  + CheckedAdd
+  + CheckedMul
+  + ToPrimitive
+  + FromPrimitive
+{
+}
+
+impl<W> Word for W where
+  W: Clone
+    + Debug
+    + Display
+    + FromStr
+    + PartialOrd
+    + Zero
+    + One
+    + CheckedAdd
+    + CheckedMul
+    + ToPrimitive
+    + FromPrimitive
+{
+}
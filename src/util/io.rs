@@ -4,8 +4,17 @@ use std::{
   path::Path,
 };
 
-fn get_input(year: u16, day: u8) -> String {
-  let path = format!("inputs/{}/d{:0>2}.txt", year, day);
+pub fn input_path(year: u16, day: u8) -> String {
+  format!("inputs/{}/d{:0>2}.txt", year, day)
+}
+
+// Expected answers to a challenge, one `<part> <answer>` line each
+pub fn answers_path(year: u16, day: u8) -> String {
+  format!("inputs/{}/d{:0>2}.answers", year, day)
+}
+
+pub fn get_input(year: u16, day: u8) -> String {
+  let path = input_path(year, day);
   if Path::new(&path).exists() {
     return path;
   }
@@ -13,8 +22,8 @@ fn get_input(year: u16, day: u8) -> String {
   panic!("input file does not exist");
 }
 
-pub fn read_file_by_lines(year: u16, day: u8) -> Vec<String> {
-  let file = File::open(get_input(year, day)).expect("no such file");
+pub fn read_file_by_lines(path: &str) -> Vec<String> {
+  let file = File::open(path).expect("no such file");
   let buf = BufReader::new(file);
 
   buf
@@ -24,8 +33,8 @@ pub fn read_file_by_lines(year: u16, day: u8) -> Vec<String> {
     .collect()
 }
 
-pub fn read_split_file(year: u16, day: u8, sep: &'static str) -> Vec<String> {
-  fs::read_to_string(get_input(year, day))
+pub fn read_split_file(path: &str, sep: &'static str) -> Vec<String> {
+  fs::read_to_string(path)
     .expect("no such file")
     .trim()
     .split(sep)
@@ -33,8 +42,8 @@ pub fn read_split_file(year: u16, day: u8, sep: &'static str) -> Vec<String> {
     .collect()
 }
 
-pub fn read_chared_file(year: u16, day: u8) -> Vec<String> {
-  fs::read_to_string(get_input(year, day))
+pub fn read_chared_file(path: &str) -> Vec<String> {
+  fs::read_to_string(path)
     .expect("no such file")
     .trim()
     .chars()
@@ -1,4 +1,5 @@
+mod context;
 pub mod intcode;
 mod io;
 
-pub use self::io::*;
+pub use self::{context::Context, io::*};